@@ -1,72 +1,291 @@
+mod builder;
 mod file_entry;
 mod file_reader;
+mod huffman;
+mod pipeline;
+mod stats;
+#[cfg(feature = "tar")]
+mod to_tar;
+mod validate;
 mod walk_dir;
 
+pub use self::builder::ArchiveReaderBuilder;
 pub use self::file_entry::Attributes;
 pub use self::file_entry::FileEntry;
 pub use self::file_entry::FileTimes;
-use self::file_reader::decompress_file_data;
+pub(crate) use self::file_reader::compress_file_data;
+pub use self::file_reader::decompress_file_data;
+pub use self::file_reader::decompress_file_data_into;
 use self::file_reader::CompressedFileReaderInner;
 pub use self::file_reader::FileReader;
 use self::file_reader::FileReaderInner;
+use self::file_reader::ReaderHandle;
+use self::file_reader::SliceFileReaderInner;
 use self::file_reader::UncompressedFileReaderInner;
+use self::huffman::huffman_decode;
+pub use self::stats::ArchiveStats;
+pub use self::validate::ValidationError;
+pub use self::walk_dir::WalkDirEntry;
 pub use self::walk_dir::WalkDirIter;
 use crate::create_key;
+use crate::create_key_v8;
+use crate::encoding_from_code_page;
+use crate::find_path_collisions;
+use crate::key_xor;
+use crate::sanitize_components;
 use crate::Error;
 use crate::Key;
+use crate::KeyString;
 use crate::DEFAULT_KEY_STRING;
-use encoding_rs::SHIFT_JIS;
-use std::cell::Cell;
-use std::cell::RefCell;
+use crate::FILE_ENTRY_SIZE;
+use crate::FILE_ENTRY_SIZE_V8;
+use crate::HEADER_SIZE_V8;
+use crate::KEY_LEN_V8;
 use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Cursor;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
-
-const FILE_ENTRY_SIZE: usize = 64;
-
-fn key_xor(position: u64, key: Key, buffer: &mut [u8]) {
-    let position_usize = usize::try_from(position).unwrap();
-    let key_len = key.len();
-
-    for (i, out_byte) in buffer.iter_mut().enumerate() {
-        let key_byte = key[(position_usize + i) % key_len];
-
-        *out_byte ^= key_byte;
-    }
-}
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::sync::RwLockWriteGuard;
+use std::sync::TryLockError;
 
 /// A reader for an archive.
+///
+/// This is `Send + Sync` if the underlying reader is, so it may be shared between threads once the header is read.
+/// Note that [`ArchiveReader::get_file_reader`] needs exclusive access to the underlying reader,
+/// so only one such file reader may be open at a time.
+/// For serving many files at once, prefer [`ArchiveReader::get_file_slice_reader`] with an in-memory or memory-mapped archive.
 #[derive(Debug)]
 pub struct ArchiveReader<R> {
-    reader: RefCell<R>,
-    position: Cell<u64>,
+    reader: RwLock<R>,
+    position: AtomicU64,
     key: Key,
 
+    /// The key string the key was created from, or `None` if the archive is not encrypted.
+    ///
+    /// Version 8 archives create a key for each file from this.
+    key_string: Option<KeyString>,
+
+    /// The offset of the archive in the underlying reader.
+    base_offset: u64,
+
     /// The string encoding.
     ///
     /// This is populated by reading the header and should not be used before.
     /// This is not a part of the header data because creating the header data requires an encoding.
     encoding: &'static encoding_rs::Encoding,
     header_data: Option<ArchiveHeaderData>,
+
+    /// An encoding to use instead of the one for the header code page.
+    encoding_override: Option<&'static encoding_rs::Encoding>,
+
+    /// Whether malformed file names are decoded lossily instead of returning an error.
+    lossy_names: bool,
+
+    /// Whether file names that don't match their upper-case names are an error.
+    strict_names: bool,
+
+    /// The maximum size of a compressed file, before and after decompression.
+    max_decompressed_size: Option<u64>,
 }
 
 impl<R> ArchiveReader<R> {
     /// Create a reader for a Data.wolf file.
     ///
-    /// Note: Currently, only version 2.20 is supported.
+    /// Use [`ArchiveReader::builder`] to combine options.
+    ///
+    /// See [`ArchiveReader::read_header`] for the supported versions.
     pub fn new(reader: R) -> Self {
-        let key = create_key(DEFAULT_KEY_STRING);
-        Self {
-            reader: RefCell::new(reader),
-            position: Cell::new(0),
-            key,
+        ArchiveReaderBuilder::new().build(reader)
+    }
+
+    /// Make a builder for combining options.
+    pub fn builder() -> ArchiveReaderBuilder {
+        ArchiveReaderBuilder::new()
+    }
+
+    /// Create a reader for a Data.wolf file that uses a custom key string.
+    ///
+    /// See [`ArchiveReader::read_header`] for the supported versions.
+    pub fn with_key_string(reader: R, key_string: KeyString) -> Self {
+        ArchiveReaderBuilder::new()
+            .key_string(key_string)
+            .build(reader)
+    }
+
+    /// Create a reader for a Data.wolf file that starts at the given offset in the reader.
+    ///
+    /// This is useful for archives that are embedded in other files, like executables.
+    /// See [`find_archive_offset`] for locating an embedded archive.
+    ///
+    /// See [`ArchiveReader::read_header`] for the supported versions.
+    pub fn new_at(reader: R, offset: u64) -> Self {
+        ArchiveReaderBuilder::new()
+            .base_offset(offset)
+            .build(reader)
+    }
+
+    /// Create a reader for a Data.wolf file that is not encrypted.
+    ///
+    /// See [`ArchiveReader::read_header`] for the supported versions.
+    pub fn new_unencrypted(reader: R) -> Self {
+        ArchiveReaderBuilder::new().unencrypted().build(reader)
+    }
+
+    /// Set the maximum size of a compressed file, before and after decompression.
+    ///
+    /// Compressed files are buffered in memory,
+    /// so this should be set when opening untrusted archives to prevent huge allocations.
+    /// Opening a compressed file that exceeds this returns an error before anything is allocated.
+    /// By default, there is no limit.
+    pub fn with_max_decompressed_size(mut self, max_decompressed_size: u64) -> Self {
+        self.max_decompressed_size = Some(max_decompressed_size);
+        self
+    }
 
-            encoding: SHIFT_JIS,
-            header_data: None,
+    /// Set the encoding used for file names, ignoring the code page in the header.
+    ///
+    /// This must be set before the header is read.
+    /// This is useful for archives with a wrong or unknown code page.
+    pub fn with_encoding(mut self, encoding: &'static encoding_rs::Encoding) -> Self {
+        self.encoding_override = Some(encoding);
+        self
+    }
+
+    /// Set whether malformed file names are decoded lossily.
+    ///
+    /// If set, malformed bytes in file names are replaced with the replacement character,
+    /// instead of failing to read the header.
+    /// This is useful for salvaging archives with corrupt file names.
+    /// By default, malformed file names are an error.
+    pub fn lossy_names(mut self, lossy_names: bool) -> Self {
+        self.lossy_names = lossy_names;
+        self
+    }
+
+    /// Set whether file names are checked against their upper-case names.
+    ///
+    /// Only the upper-case name of each file is covered by a checksum,
+    /// so this catches corrupt file names that would otherwise be read as-is.
+    /// If set, reading the header fails with [`Error::FilenameCaseMismatch`]
+    /// if a file name is not the same as its upper-case name, ignoring ASCII case.
+    /// By default, file names are not checked.
+    pub fn strict_names(mut self, strict_names: bool) -> Self {
+        self.strict_names = strict_names;
+        self
+    }
+
+    /// Unwrap this archive reader, returning the underlying reader.
+    ///
+    /// The position of the underlying reader is unspecified.
+    pub fn into_inner(self) -> R {
+        self.reader
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Try to get exclusive access to the underlying reader.
+    fn try_write_reader(&self) -> Result<RwLockWriteGuard<'_, R>, Error> {
+        match self.reader.try_write() {
+            Ok(reader) => Ok(reader),
+            // Users of the reader always seek before reading,
+            // so a panic while it was held can't leave it in a bad state.
+            Err(TryLockError::Poisoned(error)) => Ok(error.into_inner()),
+            Err(TryLockError::WouldBlock) => Err(Error::ReaderBusy),
         }
     }
 
+    /// Try to get shared access to the underlying reader.
+    fn try_read_reader(&self) -> Result<RwLockReadGuard<'_, R>, Error> {
+        match self.reader.try_read() {
+            Ok(reader) => Ok(reader),
+            Err(TryLockError::Poisoned(error)) => Ok(error.into_inner()),
+            Err(TryLockError::WouldBlock) => Err(Error::ReaderBusy),
+        }
+    }
+
+    /// Get the current position in the archive, relative to the start of the archive.
+    ///
+    /// This is mostly useful for debugging.
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// Get the archive version.
+    pub fn version(&self) -> Result<u16, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.version)
+    }
+
+    /// Get the size of the file header, in bytes.
+    ///
+    /// This is the combined size of the file name table, file table, and directory table.
+    pub fn header_size(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(u64::from(header_data.header_size))
+    }
+
+    /// Get the position of the file data, relative to the start of the archive.
+    pub fn data_position(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.data_position)
+    }
+
+    /// Get the position of the file name table, relative to the start of the archive.
+    pub fn file_name_table_position(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.file_name_table_position)
+    }
+
+    /// Get the position of the file table, relative to the file name table.
+    pub fn file_table_position(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.file_table_position)
+    }
+
+    /// Get the position of the directory table, relative to the file name table.
+    pub fn directory_table_position(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.directory_table_position)
+    }
+
+    /// Get the code page used for file names.
+    pub fn code_page(&self) -> Result<u64, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.code_page)
+    }
+
+    /// Get the encoding used for file names.
+    ///
+    /// This is selected when the header is read, and is only meaningful after.
+    pub fn encoding(&self) -> &'static encoding_rs::Encoding {
+        self.encoding
+    }
+
+    /// Get the name of the encoding used for file names, like `Shift_JIS`.
+    ///
+    /// This is selected when the header is read, and is only meaningful after.
+    pub fn encoding_name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
     /// Get the name of a file entry.
     pub fn get_file_name(&self, file_entry: &FileEntry) -> Result<&str, Error> {
         let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
@@ -79,6 +298,21 @@ impl<R> ArchiveReader<R> {
         Ok(file_name)
     }
 
+    /// Get the upper-case name of a file entry.
+    ///
+    /// Only ASCII letters are converted to upper-case.
+    /// This is useful for matching names case-insensitively, like the engine does.
+    pub fn get_upper_file_name(&self, file_entry: &FileEntry) -> Result<&str, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let upper_file_name = header_data
+            .upper_file_name_table
+            .get(&file_entry.name_position)
+            .ok_or(Error::InvalidFileNamePosition)?;
+
+        Ok(upper_file_name)
+    }
+
     /// Get a dir from a file that is for a dir.
     pub fn get_dir_from_file(&self, file_entry: &FileEntry) -> Result<&DirectoryEntry, Error> {
         let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
@@ -112,8 +346,11 @@ impl<R> ArchiveReader<R> {
             return Ok(None);
         }
 
-        let position =
-            directory.file_head_position + u64::try_from(index * FILE_ENTRY_SIZE).unwrap();
+        let position = index
+            .checked_mul(file_entry_size(header_data.version))
+            .and_then(|offset| u64::try_from(offset).ok())
+            .and_then(|offset| directory.file_head_position.checked_add(offset))
+            .ok_or(Error::IntegerOverflow)?;
         let file_entry = header_data
             .file_table
             .get(&position)
@@ -122,6 +359,52 @@ impl<R> ArchiveReader<R> {
         Ok(Some(file_entry))
     }
 
+    /// Get the immediate children of a dir, in order.
+    ///
+    /// Unlike [`ArchiveReader::read_dir`], this checks that every child exists up front.
+    /// A dir with no children yields an empty iterator.
+    pub fn get_children<'a>(
+        &'a self,
+        dir: &DirectoryEntry,
+    ) -> Result<impl ExactSizeIterator<Item = &'a FileEntry> + DoubleEndedIterator + 'a, Error>
+    {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        // Children are stored contiguously, so they are exactly the file entries in this range.
+        let num_files = usize::try_from(dir.num_files).map_err(|_| Error::IntegerOverflow)?;
+        let end = num_files
+            .checked_mul(file_entry_size(header_data.version))
+            .and_then(|size| u64::try_from(size).ok())
+            .and_then(|size| dir.file_head_position.checked_add(size))
+            .ok_or(Error::IntegerOverflow)?;
+        let children: Vec<_> = header_data
+            .file_table
+            .range(dir.file_head_position..end)
+            .map(|(_position, file_entry)| file_entry)
+            .collect();
+        if children.len() != num_files {
+            return Err(Error::InvalidDirectoryFileIndex);
+        }
+
+        Ok(children.into_iter())
+    }
+
+    /// Iterate over the immediate children of a dir, without descending into them.
+    ///
+    /// This is like [`std::fs::read_dir`].
+    /// Use [`ArchiveReader::walk_dir`] to visit all descendants.
+    pub fn read_dir<'a>(
+        &'a self,
+        dir: &'a DirectoryEntry,
+    ) -> impl Iterator<Item = Result<&'a FileEntry, Error>> + 'a {
+        let (children, error) = match self.get_children(dir) {
+            Ok(children) => (Some(children), None),
+            Err(error) => (None, Some(Err(error))),
+        };
+
+        children.into_iter().flatten().map(Ok).chain(error)
+    }
+
     /// Get the file for a dir.
     pub fn get_file_from_dir(&self, directory_entry: &DirectoryEntry) -> Result<&FileEntry, Error> {
         let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
@@ -134,11 +417,260 @@ impl<R> ArchiveReader<R> {
         Ok(file_entry)
     }
 
+    /// Get the path of a dir, relative to the root dir.
+    ///
+    /// This walks up the parents of the dir, so it is useful when starting from a dir instead of walking down from the root.
+    /// The root dir has an empty path.
+    ///
+    /// Note that this is not sanitized.
+    /// Use [`sanitize_components`] before joining untrusted paths onto an output dir.
+    pub fn dir_path(&self, dir: &DirectoryEntry) -> Result<PathBuf, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let mut components = Vec::new();
+        let mut dir = dir;
+        while let Some(parent_directory_position) = dir.parent_directory_position {
+            // Every dir can only be visited once, unless there is a cycle.
+            if components.len() >= header_data.directory_table.len() {
+                return Err(Error::DirectoryCycle);
+            }
+
+            let file_entry = self.get_file_from_dir(dir)?;
+            components.push(self.get_file_name(file_entry)?);
+
+            dir = header_data
+                .directory_table
+                .get(&parent_directory_position)
+                .ok_or(Error::InvalidDirectoryPosition)?;
+        }
+
+        Ok(components.iter().rev().collect())
+    }
+
     /// Walk over the given dir.
-    pub fn walk_dir(&self, dir: &DirectoryEntry) -> Result<WalkDirIter<R>, Error> {
+    pub fn walk_dir(&self, dir: &DirectoryEntry) -> Result<WalkDirIter<'_, R>, Error> {
+        let file_entry = self.get_file_from_dir(dir)?;
+
+        Ok(WalkDirIter::new(self, Some(file_entry), None))
+    }
+
+    /// Walk over the given dir, without descending past the given depth.
+    ///
+    /// The given dir has a depth of 0, so a max depth of 1 only visits its immediate children.
+    pub fn walk_dir_with_depth(
+        &self,
+        dir: &DirectoryEntry,
+        max_depth: usize,
+    ) -> Result<WalkDirIter<'_, R>, Error> {
         let file_entry = self.get_file_from_dir(dir)?;
 
-        Ok(WalkDirIter::new(self, file_entry))
+        Ok(WalkDirIter::new(self, Some(file_entry), Some(max_depth)))
+    }
+
+    /// Walk over the root dir.
+    ///
+    /// If the archive has no root dir, the iter is empty.
+    pub fn walk(&self) -> Result<WalkDirIter<'_, R>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let file_entry = match header_data.directory_table.get(&0) {
+            Some(root_dir) => Some(self.get_file_from_dir(root_dir)?),
+            None => None,
+        };
+
+        Ok(WalkDirIter::new(self, file_entry, None))
+    }
+
+    /// Walk the archive from the root dir, calling a callback for each entry and stopping early if asked to.
+    ///
+    /// `should_cancel` is checked before each entry.
+    /// If it returns `true`, [`Error::Cancelled`] is returned.
+    /// Errors from the callback stop the walk and are returned.
+    /// See [`ArchiveReader::walk`].
+    pub fn walk_with<C, F>(&self, should_cancel: C, mut callback: F) -> Result<(), Error>
+    where
+        C: Fn() -> bool,
+        F: FnMut(WalkDirEntry<'_>) -> Result<(), Error>,
+    {
+        for entry in self.walk()? {
+            if should_cancel() {
+                return Err(Error::Cancelled);
+            }
+
+            callback(entry?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get a file or dir by its `/`-separated path, relative to the root dir.
+    ///
+    /// Returns `None` if the path does not exist.
+    pub fn get_file_by_path(&self, path: &str) -> Result<Option<&FileEntry>, Error> {
+        self.get_file_by_path_inner(path, |component, file_entry| {
+            Ok(self.get_file_name(file_entry)? == component)
+        })
+    }
+
+    /// Get a file or dir by its `/`-separated path, relative to the root dir, ignoring ASCII case.
+    ///
+    /// This matches how the engine resolves paths.
+    /// Returns `None` if the path does not exist.
+    pub fn get_file_by_path_ci(&self, path: &str) -> Result<Option<&FileEntry>, Error> {
+        self.get_file_by_path_inner(path, |component, file_entry| {
+            let upper_file_name = self.get_upper_file_name(file_entry)?;
+            Ok(component.eq_ignore_ascii_case(upper_file_name))
+        })
+    }
+
+    /// Check if a file or dir exists at the given `/`-separated path, relative to the root dir.
+    pub fn exists(&self, path: &str) -> Result<bool, Error> {
+        Ok(self.get_file_by_path(path)?.is_some())
+    }
+
+    /// Get the metadata of a file or dir by its `/`-separated path, relative to the root dir.
+    ///
+    /// This is like [`std::fs::metadata`], but returns `None` if the path does not exist.
+    /// No file data is read.
+    pub fn metadata(&self, path: &str) -> Result<Option<&FileEntry>, Error> {
+        self.get_file_by_path(path)
+    }
+
+    /// Get a file or dir by path, using the given function to match path components against files.
+    fn get_file_by_path_inner<F>(&self, path: &str, matches: F) -> Result<Option<&FileEntry>, Error>
+    where
+        F: Fn(&str, &FileEntry) -> Result<bool, Error>,
+    {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let root_dir = match header_data.directory_table.get(&0) {
+            Some(root_dir) => root_dir,
+            None => return Ok(None),
+        };
+
+        let mut file_entry = self.get_file_from_dir(root_dir)?;
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            if !file_entry.is_dir() {
+                return Ok(None);
+            }
+            let dir = self.get_dir_from_file(file_entry)?;
+
+            let mut child = None;
+            for dir_file in self.read_dir(dir) {
+                let dir_file = dir_file?;
+                if matches(component, dir_file)? {
+                    child = Some(dir_file);
+                    break;
+                }
+            }
+
+            file_entry = match child {
+                Some(child) => child,
+                None => return Ok(None),
+            };
+        }
+
+        Ok(Some(file_entry))
+    }
+
+    /// Ensure that a file that is buffered does not exceed the maximum decompressed size.
+    fn check_decompressed_size(&self, file_entry: &FileEntry) -> Result<(), Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let max = match self.max_decompressed_size {
+            Some(max) if header_data.is_buffered(file_entry) => max,
+            _ => return Ok(()),
+        };
+
+        let size = std::cmp::max(file_entry.data_size, header_data.stored_size(file_entry)?);
+        if size > max {
+            return Err(Error::DecompressedSizeTooLarge { size, max });
+        }
+
+        Ok(())
+    }
+
+    /// Find every file and dir that matches the given predicate, in walk order.
+    ///
+    /// The predicate is given the name of each entry and its file entry.
+    /// The root dir is not checked.
+    pub fn find<F>(&self, predicate: F) -> Result<Vec<&FileEntry>, Error>
+    where
+        F: Fn(&str, &FileEntry) -> bool,
+    {
+        let mut found = Vec::new();
+        for entry in self.walk()? {
+            let entry = entry?;
+            let Some(name) = entry.path_components().last() else {
+                continue;
+            };
+            let file_entry = entry.file();
+
+            if predicate(name, file_entry) {
+                found.push(file_entry);
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Iterate over every file entry in the archive, in file table order.
+    ///
+    /// This includes the file entries for dirs.
+    pub fn files(&self) -> Result<impl Iterator<Item = &FileEntry>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        Ok(header_data.file_table.values())
+    }
+
+    /// Compute statistics about this archive.
+    ///
+    /// This iterates over the file table once, and does not read any file data.
+    pub fn stats(&self) -> Result<ArchiveStats, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let mut stats = ArchiveStats::default();
+        for (position, file_entry) in header_data.file_table.iter() {
+            // Skip the root dir.
+            if *position == 0 {
+                continue;
+            }
+
+            let stored_size = if file_entry.is_dir() {
+                0
+            } else {
+                header_data.stored_size(file_entry)?
+            };
+            stats.add(file_entry, stored_size)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Build an index of every file and dir, keyed by its full `/`-separated path.
+    ///
+    /// This walks the archive once, making later lookups cheap.
+    /// The returned entries borrow this reader.
+    pub fn build_path_index(&self) -> Result<HashMap<String, &FileEntry>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let mut index = HashMap::new();
+        let root_dir = match header_data.directory_table.get(&0) {
+            Some(root_dir) => root_dir,
+            None => return Ok(index),
+        };
+
+        for entry in self.walk_dir(root_dir)? {
+            let entry = entry?;
+            let path_components = entry.path_components();
+            if path_components.is_empty() {
+                continue;
+            }
+
+            index.insert(path_components.join("/"), entry.file());
+        }
+
+        Ok(index)
     }
 }
 
@@ -146,16 +678,122 @@ impl<R> ArchiveReader<R>
 where
     R: Read + Seek,
 {
+    /// Create the key for a file in a version 8 archive.
+    ///
+    /// The key is created from the key string followed by the upper-case names of the file and its parent dirs,
+    /// from the innermost dir out, leaving out the root dir.
+    fn create_file_key_v8(
+        &self,
+        key_string: KeyString,
+        file_entry: &FileEntry,
+    ) -> Result<[u8; KEY_LEN_V8], Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let mut source = key_string.as_bytes().to_vec();
+        let (name, _encoding, _had_errors) =
+            self.encoding.encode(self.get_upper_file_name(file_entry)?);
+        source.extend(name.iter());
+
+        let mut dir = self.parent_of_file(file_entry)?;
+        // The number of parents is limited, in case the dirs form a cycle.
+        for _ in 0..header_data.directory_table.len() {
+            let current_dir = match dir {
+                Some(current_dir) if current_dir.parent_directory_position.is_some() => current_dir,
+                _ => break,
+            };
+
+            let dir_file = self.get_file_from_dir(current_dir)?;
+            let (name, _encoding, _had_errors) =
+                self.encoding.encode(self.get_upper_file_name(dir_file)?);
+            source.extend(name.iter());
+
+            dir = self.get_parent_dir(current_dir)?;
+        }
+
+        Ok(create_key_v8(&source))
+    }
+
+    /// Decrypt the stored data of a file and undo its Huffman coding, if any.
+    ///
+    /// Compressed files stay compressed.
+    fn decode_stored_file_data(
+        &self,
+        file_entry: &FileEntry,
+        mut data: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        // Both compressed and uncompressed data are encrypted starting at the uncompressed size.
+        if header_data.version != 8 {
+            key_xor(file_entry.data_size, self.key, &mut data);
+            return Ok(data);
+        }
+        if let Some(key_string) = self.key_string.filter(|_| header_data.has_file_keys) {
+            let key = self.create_file_key_v8(key_string, file_entry)?;
+            key_xor(file_entry.data_size, key, &mut data);
+        }
+
+        let huffman_size = match file_entry.huffman_data_size {
+            Some(huffman_size) => {
+                usize::try_from(huffman_size).map_err(|_| Error::IntegerOverflow)?
+            }
+            None => return Ok(data),
+        };
+        let huffman_data = data.get(..huffman_size).ok_or(Error::DecompressionFailed)?;
+        let packed_size = file_entry
+            .compressed_data_size
+            .unwrap_or(file_entry.data_size);
+
+        match huffman_end_size(header_data.huffman_encode_kb, packed_size) {
+            Some(end_size) => {
+                // Only the ends are Huffman-coded, and the middle is stored after them.
+                let ends = huffman_decode(huffman_data, Some(2 * end_size))
+                    .ok_or(Error::DecompressionFailed)?;
+                let (head, tail) = ends.split_at(ends.len() / 2);
+                let middle = &data[huffman_size..];
+
+                let mut output = Vec::with_capacity(ends.len() + middle.len());
+                output.extend(head);
+                output.extend(middle);
+                output.extend(tail);
+
+                Ok(output)
+            }
+            None => {
+                huffman_decode(huffman_data, Some(packed_size)).ok_or(Error::DecompressionFailed)
+            }
+        }
+    }
+
+    /// Decode the stored data of a file that is buffered.
+    fn decode_file_data(&self, file_entry: &FileEntry, input: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let data = self.decode_stored_file_data(file_entry, input)?;
+
+        if file_entry.compressed_data_size.is_none() {
+            if u64::try_from(data.len()).unwrap() != file_entry.data_size {
+                return Err(Error::DecompressionFailed);
+            }
+
+            return Ok(data);
+        }
+
+        decompress_file_data(&data, file_entry.data_size)
+    }
+
     /// Read encoded bytes to a buffer.
     fn read_encoded(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
-        let mut reader = self.reader.borrow_mut();
-        reader.read_exact(buffer)?;
-
-        let position = self.position.get();
-        key_xor(position, self.key, buffer);
-        let new_position = position + u64::try_from(buffer.len()).unwrap();
+        let reader = self
+            .reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        let position = self.position.get_mut();
+        reader.read_exact(buffer).map_err(|error| Error::IoAt {
+            offset: *position,
+            error,
+        })?;
 
-        self.position.set(new_position);
+        key_xor(*position, self.key, buffer);
+        *position += u64::try_from(buffer.len()).unwrap();
 
         Ok(())
     }
@@ -183,31 +821,35 @@ where
 
     /// Read file name data.
     fn read_file_name_data(&mut self) -> Result<(String, String), Error> {
+        let offset = *self.position.get_mut();
         let len = self.read_encoded_u16()?;
         let parity = self.read_encoded_u16()?;
 
         if len == 0 {
             if parity != 0 {
-                return Err(Error::InvalidFileNameParity);
+                return Err(Error::InvalidFileNameParity { offset });
             }
 
             return Ok((String::new(), String::new()));
         }
 
-        let mut bytes_upper = vec![0; usize::from(len * 4)];
+        // Widen before multiplying, as len * 4 may overflow a u16.
+        let byte_len = usize::from(len) * 4;
+
+        let mut bytes_upper = vec![0; byte_len];
         self.read_encoded(&mut bytes_upper)?;
         let bytes_upper_parity = bytes_upper
             .iter()
             .fold(0_u16, |acc, byte| acc.wrapping_add((*byte).into()));
         if bytes_upper_parity != parity {
-            return Err(Error::InvalidFileNameParity);
+            return Err(Error::InvalidFileNameParity { offset });
         }
 
-        let mut bytes = vec![0; usize::from(len * 4)];
+        let mut bytes = vec![0; byte_len];
         self.read_encoded(&mut bytes)?;
 
         let (bytes_upper, is_malformed) = self.encoding.decode_without_bom_handling(&bytes_upper);
-        if is_malformed {
+        if is_malformed && !self.lossy_names {
             return Err(Error::InvalidFileName);
         }
         let mut bytes_upper = bytes_upper.into_owned();
@@ -216,7 +858,7 @@ where
         }
 
         let (bytes, is_malformed) = self.encoding.decode_without_bom_handling(&bytes);
-        if is_malformed {
+        if is_malformed && !self.lossy_names {
             return Err(Error::InvalidFileName);
         }
         let mut bytes = bytes.into_owned();
@@ -224,11 +866,17 @@ where
             bytes.pop();
         }
 
+        // The upper-case name is not an independent name,
+        // it is the same name with ASCII letters converted to upper-case.
+        if self.strict_names && !bytes_upper.eq_ignore_ascii_case(&bytes) {
+            return Err(Error::FilenameCaseMismatch { offset });
+        }
+
         Ok((bytes_upper, bytes))
     }
 
-    /// Read a file entry.
-    fn read_file_entry(&mut self) -> Result<FileEntry, Error> {
+    /// Read a file entry, which is at the given position relative to the file table.
+    fn read_file_entry(&mut self, version: u16, position: u64) -> Result<FileEntry, Error> {
         let name_position = self.read_encoded_u64()?;
         let attributes = self.read_encoded_u64()?;
         let created = self.read_encoded_u64()?;
@@ -237,6 +885,11 @@ where
         let data_position = self.read_encoded_u64()?;
         let data_size = self.read_encoded_u64()?;
         let compressed_data_size = self.read_encoded_u64()?;
+        let huffman_data_size = if version == 8 {
+            self.read_encoded_u64()?
+        } else {
+            u64::MAX
+        };
 
         let attributes = Attributes::from_bits_retain(attributes);
         let compressed_data_size = if compressed_data_size == u64::MAX {
@@ -244,8 +897,14 @@ where
         } else {
             Some(compressed_data_size)
         };
+        let huffman_data_size = if huffman_data_size == u64::MAX {
+            None
+        } else {
+            Some(huffman_data_size)
+        };
 
         Ok(FileEntry {
+            position,
             name_position,
             attributes,
             file_times: FileTimes {
@@ -256,6 +915,7 @@ where
             data_position,
             data_size,
             compressed_data_size,
+            huffman_data_size,
         })
     }
 
@@ -280,13 +940,55 @@ where
         })
     }
 
+    /// Seek back to the start of the archive and forget the header, so it can be read again.
+    ///
+    /// This is useful for re-reading the header with different options.
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .seek(SeekFrom::Start(self.base_offset))?;
+        *self.position.get_mut() = 0;
+        self.header_data = None;
+
+        Ok(())
+    }
+
     /// Read the header.
+    ///
+    /// Version 6 archives, like the ones made by WolfRPG Editor 2.20, are supported.
+    /// Version 8 archives can also be read, including Huffman-coded tables and files and keys for each file.
+    /// Version 8 support is experimental:
+    /// it follows the DxLib archive format, but has only been tested against archives made by this crate's tests,
+    /// not archives made by DxLib itself.
+    /// Other versions return [`Error::InvalidVersion`].
     pub fn read_header(&mut self) -> Result<(), Error> {
         if self.header_data.is_some() {
             return Err(Error::HeaderAlreadyRead);
         }
 
-        let mut magic: [u8; 2] = [0; 2];
+        // Version 8 headers are not encrypted, so check for one before decoding anything.
+        let reader = self
+            .reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        reader.seek(SeekFrom::Start(self.base_offset))?;
+        let mut raw_magic: [u8; 4] = [0; 4];
+        reader
+            .read_exact(&mut raw_magic)
+            .map_err(|error| Error::IoAt { offset: 0, error })?;
+        if raw_magic == *b"DX\x08\x00" {
+            *self.position.get_mut() = 4;
+            return self.read_header_v8();
+        }
+
+        self.reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .seek(SeekFrom::Start(self.base_offset))?;
+        *self.position.get_mut() = 0;
+
+        let mut magic: [u8; 2] = [0; 2];
         self.read_encoded(&mut magic)?;
         if magic != *b"DX" {
             return Err(Error::InvalidMagic { magic });
@@ -303,66 +1005,268 @@ where
         let file_table_position = self.read_encoded_u64()?;
         let directory_table_position = self.read_encoded_u64()?;
         let code_page = self.read_encoded_u64()?;
+        log::debug!(
+            "header: version={version} header_size={file_header_size} data_position={data_position} file_name_table_position={file_name_table_position} file_table_position={file_table_position} directory_table_position={directory_table_position} code_page={code_page}"
+        );
+
+        self.select_encoding(code_page)?;
+        validate_table_positions(
+            file_table_position,
+            directory_table_position,
+            file_header_size,
+        )?;
+        let file_name_table_start = self
+            .base_offset
+            .checked_add(file_name_table_position)
+            .ok_or(Error::InvalidHeader)?;
+
+        self.reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .seek(SeekFrom::Start(file_name_table_start))?;
+        *self.position.get_mut() = file_name_table_position;
+
+        let tables = self.read_tables(
+            version,
+            file_name_table_position,
+            file_table_position,
+            directory_table_position,
+            file_header_size,
+        )?;
+
+        self.header_data = Some(ArchiveHeaderData::new(
+            version,
+            file_header_size,
+            data_position,
+            file_name_table_position,
+            file_table_position,
+            directory_table_position,
+            code_page,
+            tables,
+        ));
+
+        Ok(())
+    }
+
+    /// Read the rest of a version 8 header, after the magic and version.
+    ///
+    /// Unlike version 6, the header is not encrypted.
+    /// The tables are stored after the file data, and are usually compressed and then Huffman-coded.
+    fn read_header_v8(&mut self) -> Result<(), Error> {
+        const FLAG_NO_KEY: u32 = 0x1;
+        const FLAG_NO_HEAD_PRESS: u32 = 0x2;
+
+        let version = 8;
 
-        self.encoding = match code_page {
-            932 => SHIFT_JIS,
-            _ => {
-                return Err(Error::UnknownCodePage { code_page });
+        let reader = self
+            .reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        // The rest of the 64 byte header.
+        let mut header = [0; 60];
+        reader
+            .read_exact(&mut header)
+            .map_err(|error| Error::IoAt { offset: 4, error })?;
+        *self.position.get_mut() = HEADER_SIZE_V8;
+
+        let read_u32 =
+            |start: usize| u32::from_le_bytes(header[start..start + 4].try_into().unwrap());
+        let read_u64 =
+            |start: usize| u64::from_le_bytes(header[start..start + 8].try_into().unwrap());
+        let file_header_size = read_u32(0);
+        let data_position = read_u64(4);
+        let file_name_table_position = read_u64(12);
+        let file_table_position = read_u64(20);
+        let directory_table_position = read_u64(28);
+        let code_page = u64::from(read_u32(36));
+        let flags = read_u32(40);
+        let huffman_encode_kb = header[44];
+        log::debug!(
+            "header: version={version} header_size={file_header_size} data_position={data_position} file_name_table_position={file_name_table_position} file_table_position={file_table_position} directory_table_position={directory_table_position} code_page={code_page} flags={flags:#X} huffman_encode_kb={huffman_encode_kb}"
+        );
+
+        self.select_encoding(code_page)?;
+        validate_table_positions(
+            file_table_position,
+            directory_table_position,
+            file_header_size,
+        )?;
+        let file_name_table_start = self
+            .base_offset
+            .checked_add(file_name_table_position)
+            .ok_or(Error::InvalidHeader)?;
+
+        let reader = self
+            .reader
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        reader.seek(SeekFrom::Start(file_name_table_start))?;
+        let mut tables = Vec::new();
+        if flags & FLAG_NO_HEAD_PRESS != 0 {
+            reader
+                .by_ref()
+                .take(u64::from(file_header_size))
+                .read_to_end(&mut tables)?;
+            if tables.len() != usize::try_from(file_header_size).unwrap() {
+                return Err(Error::Io(std::io::ErrorKind::UnexpectedEof.into()));
             }
-        };
+        } else {
+            // The packed tables run to the end of the archive.
+            let mut packed = Vec::new();
+            reader.read_to_end(&mut packed)?;
+            let compressed = huffman_decode(&packed, None).ok_or(Error::DecompressionFailed)?;
+            decompress_file_data_into(&compressed, &mut tables)?;
+            if tables.len() != usize::try_from(file_header_size).unwrap() {
+                return Err(Error::DecompressionFailed);
+            }
+        }
+
+        let has_key = flags & FLAG_NO_KEY == 0;
+        if has_key {
+            if let Some(key_string) = self.key_string {
+                key_xor(0, create_key_v8(key_string.as_bytes()), &mut tables);
+            }
+        }
 
-        self.position.set(
-            self.reader
-                .borrow_mut()
-                .seek(SeekFrom::Start(file_name_table_position))?,
+        // The tables are already decoded, so read them with an unencrypted reader over them.
+        let mut table_reader = ArchiveReaderBuilder::new()
+            .unencrypted()
+            .lossy_names(self.lossy_names)
+            .strict_names(self.strict_names)
+            .build(Cursor::new(tables));
+        table_reader.encoding = self.encoding;
+        *table_reader.position.get_mut() = file_name_table_position;
+        let tables = table_reader.read_tables(
+            version,
+            file_name_table_position,
+            file_table_position,
+            directory_table_position,
+            file_header_size,
+        )?;
+
+        let mut header_data = ArchiveHeaderData::new(
+            version,
+            file_header_size,
+            data_position,
+            file_name_table_position,
+            file_table_position,
+            directory_table_position,
+            code_page,
+            tables,
         );
+        header_data.has_file_keys = has_key;
+        header_data.huffman_encode_kb = huffman_encode_kb;
+        self.header_data = Some(header_data);
 
-        let mut file_name_table = BTreeMap::new();
-        let mut file_table = BTreeMap::new();
-        let mut directory_table = BTreeMap::new();
+        Ok(())
+    }
+
+    /// Select the encoding for file names, unless it was overridden.
+    fn select_encoding(&mut self, code_page: u64) -> Result<(), Error> {
+        self.encoding = match self.encoding_override {
+            Some(encoding) => encoding,
+            None => {
+                encoding_from_code_page(code_page).ok_or(Error::UnknownCodePage { code_page })?
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Read the file name, file, and directory tables.
+    ///
+    /// The reader must be at the start of the file name table.
+    fn read_tables(
+        &mut self,
+        version: u16,
+        file_name_table_position: u64,
+        file_table_position: u64,
+        directory_table_position: u64,
+        file_header_size: u32,
+    ) -> Result<Tables, Error> {
+        let get_header_position = |position: u64| {
+            position
+                .checked_sub(file_name_table_position)
+                .ok_or(Error::InvalidHeader)
+        };
+        let file_entry_size = u64::try_from(file_entry_size(version)).unwrap();
+
+        let mut tables = Tables::default();
 
         loop {
-            let relative_position = self.position.get() - file_name_table_position;
+            let relative_position = get_header_position(*self.position.get_mut())?;
             if relative_position >= file_table_position {
                 break;
             }
 
-            let (_upper_file_name, file_name) = self.read_file_name_data()?;
-            file_name_table.insert(relative_position, file_name);
+            let (upper_file_name, file_name) = self.read_file_name_data()?;
+            tables
+                .upper_file_name_table
+                .insert(relative_position, upper_file_name);
+            tables.file_name_table.insert(relative_position, file_name);
         }
 
         loop {
-            let header_position = self.position.get() - file_name_table_position;
+            let header_position = get_header_position(*self.position.get_mut())?;
             if header_position >= directory_table_position {
                 break;
             }
-            let relative_position =
-                self.position.get() - file_name_table_position - file_table_position;
+            let relative_position = header_position
+                .checked_sub(file_table_position)
+                .ok_or(Error::InvalidHeader)?;
 
-            let file_entry = self.read_file_entry()?;
-            file_table.insert(relative_position, file_entry);
+            let file_entry = self.read_file_entry(version, relative_position)?;
+            tables.file_table.insert(relative_position, file_entry);
         }
 
         loop {
-            let header_position = self.position.get() - file_name_table_position;
+            let header_position = get_header_position(*self.position.get_mut())?;
             if header_position >= u64::from(file_header_size) {
                 break;
             }
-            let relative_position =
-                self.position.get() - file_name_table_position - directory_table_position;
+            let relative_position = header_position
+                .checked_sub(directory_table_position)
+                .ok_or(Error::InvalidHeader)?;
 
             let directory_entry = self.read_directory_entry()?;
-            directory_table.insert(relative_position, directory_entry);
+            log::trace!("dir at {relative_position}: {directory_entry:?}");
+
+            // A corrupt file count could make walks iterate over a huge number of missing files,
+            // so make sure all of the children fit in the file table.
+            let file_table_size = directory_table_position - file_table_position;
+            let children_end = directory_entry
+                .num_files
+                .checked_mul(file_entry_size)
+                .and_then(|size| directory_entry.file_head_position.checked_add(size));
+            if children_end.is_none_or(|children_end| children_end > file_table_size) {
+                return Err(Error::InvalidHeader);
+            }
+
+            tables
+                .directory_table
+                .insert(relative_position, directory_entry);
         }
 
-        self.header_data = Some(ArchiveHeaderData {
-            data_position,
-            file_name_table,
-            file_table,
-            directory_table,
-        });
+        // Map each file to the dir that lists it, so parents can be found without scanning the dir table.
+        for (directory_position, directory_entry) in tables.directory_table.iter() {
+            for index in 0..directory_entry.num_files {
+                // This can't overflow, as the children were checked to fit in the file table.
+                let file_position = directory_entry.file_head_position + index * file_entry_size;
+                tables
+                    .file_parents
+                    .entry(file_position)
+                    .or_insert(*directory_position);
+            }
+        }
 
-        Ok(())
+        log::debug!(
+            "read {} file names, {} files, and {} dirs",
+            tables.file_name_table.len(),
+            tables.file_table.len(),
+            tables.directory_table.len()
+        );
+
+        Ok(tables)
     }
 
     /// Get the root directory
@@ -392,76 +1296,681 @@ where
         Ok(Some(directory_entry))
     }
 
+    /// Get the dir that contains a file or dir.
+    ///
+    /// The root dir is not contained in any dir, so this returns `None` for it.
+    pub fn parent_of_file(&self, file_entry: &FileEntry) -> Result<Option<&DirectoryEntry>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        let directory_position = match header_data.file_parents.get(&file_entry.position) {
+            Some(directory_position) => directory_position,
+            None => return Ok(None),
+        };
+
+        let directory_entry = header_data
+            .directory_table
+            .get(directory_position)
+            .ok_or(Error::InvalidDirectoryPosition)?;
+
+        Ok(Some(directory_entry))
+    }
+
     /// Get a file reader.
-    pub fn get_file_reader(&self, file_entry: &FileEntry) -> Result<FileReader<R>, Error> {
+    pub fn get_file_reader(&self, file_entry: &FileEntry) -> Result<FileReader<'_, R>, Error> {
+        let reader = self.try_write_reader()?;
+
+        self.create_file_reader(ReaderHandle::Borrowed(reader), file_entry)
+    }
+
+    /// Get a file reader for a file by its `/`-separated path, relative to the root dir.
+    ///
+    /// Returns [`Error::NotFound`] if the path does not exist, or [`Error::NotAFile`] if it is a dir.
+    pub fn get_file_reader_by_path(&self, path: &str) -> Result<FileReader<'_, R>, Error> {
+        let file_entry = self
+            .get_file_by_path(path)?
+            .ok_or_else(|| Error::NotFound {
+                path: path.to_string(),
+            })?;
+
+        self.get_file_reader(file_entry)
+    }
+
+    /// Get a file reader that owns a clone of the underlying reader.
+    ///
+    /// Unlike [`ArchiveReader::get_file_reader`], this does not hold a borrow on this archive reader,
+    /// so multiple file readers may be open at once.
+    ///
+    /// Each clone must have its own position, like a `Cursor` or a [`SharedFile`](crate::SharedFile).
+    /// `File` is not `Clone`, and clones that share a position, like `&File` or a `File` from `File::try_clone`,
+    /// will corrupt each other's reads if they are used from different threads.
+    /// To read a file on disk, open it as a [`SharedFile`](crate::SharedFile), which uses positional reads.
+    ///
+    /// Note that each clone may have its own cost.
+    /// For example, a `Cursor<Vec<u8>>` clones its data, while a `SharedFile` only clones a handle.
+    pub fn get_file_reader_owned<'a>(
+        &self,
+        file_entry: &FileEntry,
+    ) -> Result<FileReader<'a, R>, Error>
+    where
+        R: Clone + 'a,
+    {
+        let reader = self.try_read_reader()?.clone();
+
+        self.create_file_reader(ReaderHandle::Owned(reader), file_entry)
+    }
+
+    /// Read the data of a file as it is stored, without decompressing it.
+    ///
+    /// The data is decrypted and any Huffman coding is undone, but compressed files stay compressed.
+    /// This is useful for copying files to another archive without recompressing them.
+    pub fn read_raw_file_data(&self, file_entry: &FileEntry) -> Result<Vec<u8>, Error> {
         let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
 
         if file_entry.is_dir() {
             return Err(Error::NotAFile);
         }
 
-        let mut reader = self
-            .reader
-            .try_borrow_mut()
-            .map_err(|_| Error::ReaderBusy)?;
-
-        let new_position = reader.seek(SeekFrom::Start(
-            header_data.data_position + file_entry.data_position,
-        ))?;
-
-        self.position.set(new_position);
-        match file_entry.compressed_data_size {
-            Some(compressed_size) => {
-                // Yes, we secretly buffer compressed files.
-                // This is because the compression protocol seems to refer to byte sequences from the output,
-                // which is difficult to wrap with a Read interface.
-                //
-                // We could choose use the compressed data via the Read interface,
-                // but that wouldn't save too much data and add more complexity,
-                // as we would still need to buffer the entire output in memory.
-                let mut input = Vec::with_capacity(usize::try_from(compressed_size).unwrap());
-                reader
-                    .by_ref()
-                    .take(compressed_size)
-                    .read_to_end(&mut input)?;
-                key_xor(file_entry.data_size, self.key, &mut input);
+        self.check_decompressed_size(file_entry)?;
 
-                let output = decompress_file_data(&input, file_entry.data_size)
-                    .ok_or(Error::DecompressionFailed)?;
+        let stored_size = header_data.stored_size(file_entry)?;
+        let start = self
+            .base_offset
+            .checked_add(header_data.data_position)
+            .and_then(|start| start.checked_add(file_entry.data_position))
+            .ok_or(Error::IntegerOverflow)?;
 
-                Ok(FileReader {
-                    inner: FileReaderInner::Compressed(CompressedFileReaderInner {
-                        file_data: std::io::Cursor::new(output),
-                    }),
-                })
-            }
+        let mut reader = self.try_write_reader()?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        let data = read_exact_untrusted(&mut *reader, start - self.base_offset, stored_size)?;
+        self.position
+            .store(start - self.base_offset + stored_size, Ordering::Relaxed);
+        drop(reader);
+
+        self.decode_stored_file_data(file_entry, data)
+    }
+
+    /// Create a file reader from a reader handle.
+    fn create_file_reader<'a>(
+        &self,
+        mut reader: ReaderHandle<'a, R>,
+        file_entry: &FileEntry,
+    ) -> Result<FileReader<'a, R>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        if file_entry.is_dir() {
+            return Err(Error::NotAFile);
+        }
+
+        self.check_decompressed_size(file_entry)?;
+
+        let start = self
+            .base_offset
+            .checked_add(header_data.data_position)
+            .and_then(|start| start.checked_add(file_entry.data_position))
+            .ok_or(Error::IntegerOverflow)?;
+        let new_position = reader.seek(SeekFrom::Start(start))?;
+
+        if let ReaderHandle::Borrowed(_) = reader {
+            self.position
+                .store(new_position - self.base_offset, Ordering::Relaxed);
+        }
+        if header_data.is_buffered(file_entry) {
+            // Yes, we secretly buffer compressed files.
+            // This is because the compression protocol seems to refer to byte sequences from the output,
+            // which is difficult to wrap with a Read interface.
+            //
+            // We could choose use the compressed data via the Read interface,
+            // but that wouldn't save too much data and add more complexity,
+            // as we would still need to buffer the entire output in memory.
+            let stored_size = header_data.stored_size(file_entry)?;
+            let input = read_exact_untrusted(&mut *reader, start - self.base_offset, stored_size)?;
+            let output = self.decode_file_data(file_entry, input)?;
+
+            return Ok(FileReader {
+                inner: FileReaderInner::Compressed(CompressedFileReaderInner {
+                    file_data: Cursor::new(output),
+                }),
+            });
+        }
+
+        let reader = UncompressedFileReaderInner {
+            reader,
+            key: self.key,
+            start,
+            offset: 0,
+            size: file_entry.data_size,
+        };
+
+        Ok(FileReader {
+            inner: FileReaderInner::Uncompressed(reader),
+        })
+    }
+
+    /// Read the entire contents of a file by its `/`-separated path, relative to the root dir.
+    ///
+    /// This is like [`std::fs::read`].
+    /// Returns [`Error::NotFound`] if the path does not exist, or [`Error::NotAFile`] if it is a dir.
+    pub fn read(&self, path: &str) -> Result<Vec<u8>, Error> {
+        let file_entry = self
+            .get_file_by_path(path)?
+            .ok_or_else(|| Error::NotFound {
+                path: path.to_string(),
+            })?;
+        let mut reader = self.get_file_reader(file_entry)?;
+
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Read the entire contents of a text file by its `/`-separated path, relative to the root dir.
+    ///
+    /// The text is decoded with the archive's [`encoding`](ArchiveReader::encoding).
+    /// A leading byte order mark for that encoding is removed.
+    /// Returns [`Error::InvalidText`] if the file is not valid text in that encoding.
+    pub fn read_to_string(&self, path: &str) -> Result<String, Error> {
+        let data = self.read(path)?;
+
+        let (text, is_malformed) = self.encoding.decode_with_bom_removal(&data);
+        if is_malformed {
+            return Err(Error::InvalidText);
+        }
+
+        Ok(text.into_owned())
+    }
+
+    /// Copy the contents of a file, by its `/`-separated path relative to the root dir, into a writer.
+    ///
+    /// This returns the number of bytes copied.
+    /// Returns [`Error::NotFound`] if the path does not exist, or [`Error::NotAFile`] if it is a dir.
+    pub fn extract_file_to<W>(&self, path: &str, out: &mut W) -> Result<u64, Error>
+    where
+        W: Write + ?Sized,
+    {
+        let mut reader = self.get_file_reader_by_path(path)?;
+
+        Ok(std::io::copy(&mut reader, out)?)
+    }
+
+    /// Extract every file and dir in this archive into the given dir.
+    ///
+    /// Paths that would escape the destination dir are rejected with [`sanitize_components`].
+    ///
+    /// If the archive has no root dir, no files have paths, so nothing is extracted.
+    /// If two files have paths that only differ by case, [`Error::PathCollision`] is returned before anything is extracted,
+    /// as they would overwrite each other on case-insensitive filesystems.
+    pub fn extract_all(&self, dest: &Path) -> Result<(), Error> {
+        self.extract_all_cancellable(dest, || false)
+    }
+
+    /// Extract every file and dir in this archive into the given dir, stopping early if asked to.
+    ///
+    /// `should_cancel` is checked before each entry is extracted.
+    /// If it returns `true`, [`Error::Cancelled`] is returned, leaving the entries that were already extracted.
+    /// See [`ArchiveReader::extract_all`].
+    pub fn extract_all_cancellable<C>(&self, dest: &Path, should_cancel: C) -> Result<(), Error>
+    where
+        C: Fn() -> bool,
+    {
+        self.extract_all_inner(dest, should_cancel, |_path, _done, _total| {})
+    }
+
+    /// Extract every file and dir in this archive into the given dir, reporting progress.
+    ///
+    /// `on_progress` is called after each file is extracted,
+    /// with the path it was extracted to, the number of files extracted so far, and the total number of files.
+    /// See [`ArchiveReader::extract_all`].
+    pub fn extract_all_with_progress<P>(&self, dest: &Path, on_progress: P) -> Result<(), Error>
+    where
+        P: FnMut(&Path, u64, u64),
+    {
+        self.extract_all_inner(dest, || false, on_progress)
+    }
+
+    /// Check that an archive can be extracted and create the destination dir.
+    ///
+    /// This returns the root dir and the number of files,
+    /// or `None` if the archive has no root dir, as there is nothing to extract.
+    fn prepare_extract_all(&self, dest: &Path) -> Result<Option<(&DirectoryEntry, u64)>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        std::fs::create_dir_all(dest)?;
+
+        let root_dir = match header_data.directory_table.get(&0) {
+            Some(root_dir) => root_dir,
             None => {
-                let reader = UncompressedFileReaderInner {
-                    reader,
-                    key: self.key,
-                    offset: 0,
-                    size: file_entry.data_size,
-                };
-
-                Ok(FileReader {
-                    inner: FileReaderInner::Uncompressed(reader),
-                })
+                log::warn!("the archive has no root dir, so there is nothing to extract");
+                return Ok(None);
             }
+        };
+
+        let file_paths = self
+            .walk_dir(root_dir)?
+            .files_only()
+            .map(|entry| Ok(entry?.path_components().join("/")))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if let Some(&(first, second)) = find_path_collisions(&file_paths).first() {
+            return Err(Error::PathCollision {
+                first: file_paths[first].clone(),
+                second: file_paths[second].clone(),
+            });
         }
+
+        Ok(Some((root_dir, u64::try_from(file_paths.len()).unwrap())))
+    }
+
+    /// The implementation of the `extract_all` methods.
+    fn extract_all_inner<C, P>(
+        &self,
+        dest: &Path,
+        should_cancel: C,
+        mut on_progress: P,
+    ) -> Result<(), Error>
+    where
+        C: Fn() -> bool,
+        P: FnMut(&Path, u64, u64),
+    {
+        let (root_dir, total) = match self.prepare_extract_all(dest)? {
+            Some(prepared) => prepared,
+            None => return Ok(()),
+        };
+
+        let mut done = 0;
+        for entry in self.walk_dir(root_dir)? {
+            if should_cancel() {
+                return Err(Error::Cancelled);
+            }
+
+            let entry = entry?;
+            let file = entry.file();
+
+            let path = dest.join(sanitize_components(entry.path_components())?);
+
+            if file.is_dir() {
+                std::fs::create_dir_all(path)?;
+            } else {
+                let mut reader = self.get_file_reader(file)?;
+                let mut file = File::create(&path)?;
+                std::io::copy(&mut reader, &mut file)?;
+
+                done += 1;
+                on_progress(&path, done, total);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a file, passing each decoded chunk to the given callback.
+    ///
+    /// This avoids buffering the entire file for uncompressed files.
+    /// Compressed files are still buffered internally, but are passed to the callback in chunks.
+    pub fn read_file_chunks<F>(&self, file_entry: &FileEntry, mut chunk: F) -> Result<(), Error>
+    where
+        F: FnMut(&[u8]) -> std::io::Result<()>,
+    {
+        let mut reader = self.get_file_reader(file_entry)?;
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let n = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(Error::Io(error)),
+            };
+
+            chunk(&buffer[..n])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T> ArchiveReader<Cursor<T>>
+where
+    T: AsRef<[u8]>,
+{
+    /// Get a file reader that reads directly from the in-memory archive data.
+    ///
+    /// Uncompressed files are decrypted lazily into the caller's buffer as they are read,
+    /// without seeking or copying the whole file up front.
+    /// This makes it the preferred way to read from in-memory or memory-mapped archives.
+    ///
+    /// This only holds a shared borrow on the underlying reader,
+    /// so multiple slice readers may be open at once.
+    pub fn get_file_slice_reader(
+        &self,
+        file_entry: &FileEntry,
+    ) -> Result<FileReader<'_, Cursor<T>>, Error> {
+        let header_data = self.header_data.as_ref().ok_or(Error::HeaderNotRead)?;
+
+        if file_entry.is_dir() {
+            return Err(Error::NotAFile);
+        }
+
+        self.check_decompressed_size(file_entry)?;
+
+        let reader = self.try_read_reader()?;
+
+        let start = self
+            .base_offset
+            .checked_add(header_data.data_position)
+            .and_then(|start| start.checked_add(file_entry.data_position))
+            .ok_or(Error::IntegerOverflow)?;
+        let stored_size = header_data.stored_size(file_entry)?;
+        let start = usize::try_from(start).map_err(|_| Error::InvalidFilePosition)?;
+        let stored_size = usize::try_from(stored_size).map_err(|_| Error::InvalidFilePosition)?;
+        let end = start
+            .checked_add(stored_size)
+            .ok_or(Error::InvalidFilePosition)?;
+        if end > reader.get_ref().as_ref().len() {
+            return Err(Error::InvalidFilePosition);
+        }
+
+        if header_data.is_buffered(file_entry) {
+            let input = reader.get_ref().as_ref()[start..end].to_vec();
+            let output = self.decode_file_data(file_entry, input)?;
+
+            return Ok(FileReader {
+                inner: FileReaderInner::Compressed(CompressedFileReaderInner {
+                    file_data: Cursor::new(output),
+                }),
+            });
+        }
+
+        Ok(FileReader {
+            inner: FileReaderInner::Slice(SliceFileReaderInner {
+                reader,
+                get_data: get_cursor_data::<T>,
+                start,
+                end,
+                key: self.key,
+                offset: 0,
+            }),
+        })
+    }
+}
+
+/// Get the data of a cursor.
+fn get_cursor_data<T>(cursor: &Cursor<T>) -> &[u8]
+where
+    T: AsRef<[u8]>,
+{
+    cursor.get_ref().as_ref()
+}
+
+impl ArchiveReader<Cursor<Vec<u8>>> {
+    /// Create a reader for a Data.wolf file that is already in memory.
+    ///
+    /// The header still needs to be read with [`ArchiveReader::read_header`].
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self::new(Cursor::new(data))
+    }
+
+    /// Create a reader for a Data.wolf file by reading all of the given reader into memory.
+    ///
+    /// This is useful for sources that can't seek, like stdin or a network stream.
+    /// The header still needs to be read with [`ArchiveReader::read_header`].
+    pub fn from_reader_buffered<R>(mut reader: R) -> Result<Self, Error>
+    where
+        R: Read,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Ok(Self::from_bytes(data))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl ArchiveReader<Cursor<memmap2::Mmap>> {
+    /// Create a reader for a Data.wolf file by memory-mapping it.
+    ///
+    /// This avoids syscalls when seeking around the archive,
+    /// and allows using [`ArchiveReader::get_file_slice_reader`].
+    /// The map is owned by the reader, so it lives as long as the reader does.
+    /// The header still needs to be read with [`ArchiveReader::read_header`].
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated while the reader exists,
+    /// as that is undefined behavior for memory-mapped files.
+    pub unsafe fn from_path_mmap<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path)?;
+        let mmap = memmap2::Mmap::map(&file)?;
+
+        Ok(Self::new(Cursor::new(mmap)))
+    }
+}
+
+/// Find the offset of an archive embedded in another file, like an executable.
+///
+/// This scans the reader from the start for the encoded magic and version of an archive that uses the default key.
+/// Use [`ArchiveReaderBuilder::find_archive_offset`] for archives with a custom key or no encryption.
+/// The reader is read in fixed-size chunks, so the whole file is never loaded into memory.
+/// Returns `None` if no archive was found.
+/// The position of the reader is unspecified after this returns.
+pub fn find_archive_offset<R>(reader: &mut R) -> Result<Option<u64>, Error>
+where
+    R: Read + Seek,
+{
+    find_archive_offset_with_key(reader, create_key(DEFAULT_KEY_STRING))
+}
+
+/// Find the offset of an archive embedded in another file, for an archive that uses the given key.
+///
+/// See [`find_archive_offset`].
+fn find_archive_offset_with_key<R>(reader: &mut R, key: Key) -> Result<Option<u64>, Error>
+where
+    R: Read + Seek,
+{
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    let mut pattern = *b"DX\x06\x00";
+    key_xor(0, key, &mut pattern);
+    let overlap = pattern.len() - 1;
+
+    reader.seek(SeekFrom::Start(0))?;
+
+    // The offset of the start of the buffer in the reader.
+    let mut buffer_offset = 0;
+    let mut buffer = Vec::with_capacity(CHUNK_SIZE + overlap);
+    loop {
+        let buffer_len = buffer.len();
+        let n = reader
+            .by_ref()
+            .take(u64::try_from(CHUNK_SIZE).unwrap())
+            .read_to_end(&mut buffer)?;
+        if n == 0 {
+            return Ok(None);
+        }
+
+        // Only check windows that include new bytes, as the others were checked in the last chunk.
+        let search_start = buffer_len.saturating_sub(overlap);
+        if let Some(index) = buffer[search_start..]
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+        {
+            let index = u64::try_from(search_start + index).unwrap();
+            return Ok(Some(buffer_offset + index));
+        }
+
+        // Keep the tail of the buffer, in case the magic is split across chunks.
+        let keep_start = buffer.len().saturating_sub(overlap);
+        buffer_offset += u64::try_from(keep_start).unwrap();
+        buffer.drain(..keep_start);
+    }
+}
+
+/// Get the size of a file entry in the file table for an archive version.
+fn file_entry_size(version: u16) -> usize {
+    if version == 8 {
+        FILE_ENTRY_SIZE_V8
+    } else {
+        FILE_ENTRY_SIZE
     }
 }
 
+/// Check that the tables are laid out in order.
+///
+/// The table positions are relative to the file name table.
+/// Validate this up front, as the loops that read the tables rely on it to terminate.
+fn validate_table_positions(
+    file_table_position: u64,
+    directory_table_position: u64,
+    file_header_size: u32,
+) -> Result<(), Error> {
+    if file_table_position > directory_table_position
+        || directory_table_position > u64::from(file_header_size)
+    {
+        return Err(Error::InvalidHeader);
+    }
+
+    Ok(())
+}
+
+/// The tables read from the header
+#[derive(Debug, Default)]
+struct Tables {
+    file_name_table: BTreeMap<u64, String>,
+    upper_file_name_table: BTreeMap<u64, String>,
+    file_table: BTreeMap<u64, FileEntry>,
+    directory_table: BTreeMap<u64, DirectoryEntry>,
+
+    /// The positions of the dirs that list each file, by file position.
+    file_parents: HashMap<u64, u64>,
+}
+
 /// Data extracted from the header
 #[derive(Debug)]
 struct ArchiveHeaderData {
+    version: u16,
+    header_size: u32,
     data_position: u64,
+    file_name_table_position: u64,
+    file_table_position: u64,
+    directory_table_position: u64,
+    code_page: u64,
     file_name_table: BTreeMap<u64, String>,
+    upper_file_name_table: BTreeMap<u64, String>,
     file_table: BTreeMap<u64, FileEntry>,
     directory_table: BTreeMap<u64, DirectoryEntry>,
+
+    /// The positions of the dirs that list each file, by file position.
+    file_parents: HashMap<u64, u64>,
+
+    /// Whether each file is encrypted with its own key, which is only done by version 8 archives.
+    has_file_keys: bool,
+
+    /// The number of KiB at each end of a file that are Huffman-coded, for version 8 archives.
+    ///
+    /// `u8::MAX` means that whole files are Huffman-coded.
+    huffman_encode_kb: u8,
+}
+
+/// Get the size of each Huffman-coded end of a file in a version 8 archive,
+/// or `None` if the whole file is Huffman-coded.
+fn huffman_end_size(huffman_encode_kb: u8, size: u64) -> Option<u64> {
+    if huffman_encode_kb == u8::MAX {
+        return None;
+    }
+
+    let end_size = u64::from(huffman_encode_kb) * 1024;
+    if size <= end_size * 2 {
+        return None;
+    }
+
+    Some(end_size)
+}
+
+/// Read exactly `size` bytes, where `size` comes from the archive and may be corrupt.
+///
+/// The buffer grows as data is read instead of being preallocated,
+/// so a huge size can't make this allocate more than the reader holds.
+/// `offset` is the position of the data in the archive, and is reported if it is cut short.
+fn read_exact_untrusted<R>(reader: &mut R, offset: u64, size: u64) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
+    let mut data = Vec::new();
+    reader
+        .take(size)
+        .read_to_end(&mut data)
+        .map_err(|error| Error::IoAt { offset, error })?;
+    if u64::try_from(data.len()).unwrap() != size {
+        return Err(Error::IoAt {
+            offset,
+            error: std::io::ErrorKind::UnexpectedEof.into(),
+        });
+    }
+
+    Ok(data)
+}
+
+impl ArchiveHeaderData {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        version: u16,
+        header_size: u32,
+        data_position: u64,
+        file_name_table_position: u64,
+        file_table_position: u64,
+        directory_table_position: u64,
+        code_page: u64,
+        tables: Tables,
+    ) -> Self {
+        Self {
+            version,
+            header_size,
+            data_position,
+            file_name_table_position,
+            file_table_position,
+            directory_table_position,
+            code_page,
+            file_name_table: tables.file_name_table,
+            upper_file_name_table: tables.upper_file_name_table,
+            file_table: tables.file_table,
+            directory_table: tables.directory_table,
+            file_parents: tables.file_parents,
+            has_file_keys: false,
+            huffman_encode_kb: u8::MAX,
+        }
+    }
+
+    /// Whether a file is decoded into memory when it is opened.
+    ///
+    /// Compressed files are always buffered.
+    /// Files in version 8 archives are also buffered, as they may be Huffman-coded or have their own key.
+    fn is_buffered(&self, file_entry: &FileEntry) -> bool {
+        self.version == 8 || file_entry.compressed_data_size.is_some()
+    }
+
+    /// Get the number of bytes that the data of a file takes up in the archive.
+    fn stored_size(&self, file_entry: &FileEntry) -> Result<u64, Error> {
+        let packed_size = file_entry
+            .compressed_data_size
+            .unwrap_or(file_entry.data_size);
+        let huffman_size = match file_entry.huffman_data_size {
+            Some(huffman_size) => huffman_size,
+            None => return Ok(packed_size),
+        };
+
+        match huffman_end_size(self.huffman_encode_kb, packed_size) {
+            Some(end_size) => huffman_size
+                .checked_add(packed_size - 2 * end_size)
+                .ok_or(Error::IntegerOverflow),
+            None => Ok(huffman_size),
+        }
+    }
 }
 
 /// The header for a directory entry
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DirectoryEntry {
     directory_position: u64,
     parent_directory_position: Option<u64>,
@@ -470,8 +1979,1170 @@ pub struct DirectoryEntry {
 }
 
 impl DirectoryEntry {
+    /// Get the position of the file entry for this dir, relative to the file table.
+    pub fn directory_position(&self) -> u64 {
+        self.directory_position
+    }
+
+    /// Get the position of the parent dir entry, relative to the directory table.
+    ///
+    /// This is `None` for the root dir.
+    pub fn parent_directory_position(&self) -> Option<u64> {
+        self.parent_directory_position
+    }
+
     /// Get the number of files in this dir.
     pub fn num_files(&self) -> u64 {
         self.num_files
     }
+
+    /// Get the position of the first child file entry, relative to the file table.
+    ///
+    /// The children are stored next to each other in the file table.
+    pub fn file_head_position(&self) -> u64 {
+        self.file_head_position
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::huffman::huffman_encode;
+    use super::*;
+    use crate::ArchiveWriter;
+
+    #[test]
+    fn seek_file_reader() {
+        let data: Vec<u8> = (0..=u8::MAX).cycle().take(1000).collect();
+
+        for compression in [false, true] {
+            let mut writer =
+                ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(compression);
+            writer.add_file("data.bin", &data).unwrap();
+            let archive = writer.finish().unwrap().into_inner();
+
+            let mut reader = ArchiveReader::new(Cursor::new(archive));
+            reader.read_header().unwrap();
+            let file_entry = reader.get_file_by_path("data.bin").unwrap().unwrap();
+            assert!(file_entry.is_compressed() == compression);
+
+            let check_seek = |mut file_reader: FileReader<'_, Cursor<Vec<u8>>>| {
+                let mut buffer = [0; 4];
+
+                file_reader.seek(SeekFrom::Start(500)).unwrap();
+                file_reader.read_exact(&mut buffer).unwrap();
+                assert!(buffer == data[500..504]);
+
+                file_reader.seek(SeekFrom::Current(-104)).unwrap();
+                file_reader.read_exact(&mut buffer).unwrap();
+                assert!(buffer == data[400..404]);
+
+                file_reader.seek(SeekFrom::End(-4)).unwrap();
+                file_reader.read_exact(&mut buffer).unwrap();
+                assert!(buffer == data[996..]);
+
+                assert!(file_reader.seek(SeekFrom::End(10)).unwrap() == 1000);
+                assert!(file_reader.read(&mut buffer).unwrap() == 0);
+                assert!(file_reader.seek(SeekFrom::Current(-1001)).is_err());
+            };
+            check_seek(reader.get_file_reader(file_entry).unwrap());
+            check_seek(reader.get_file_reader_owned(file_entry).unwrap());
+            check_seek(reader.get_file_slice_reader(file_entry).unwrap());
+        }
+    }
+
+    #[cfg(any(unix, windows))]
+    #[test]
+    fn owned_file_readers_across_threads() {
+        let a: Vec<u8> = (0..=u8::MAX).cycle().take(100_000).collect();
+        let b: Vec<u8> = a.iter().rev().copied().collect();
+
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(false);
+        writer.add_file("a.bin", &a).unwrap();
+        writer.add_file("b.bin", &b).unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let path = std::env::temp_dir().join("wolf-rpg-data-owned-file-readers");
+        std::fs::write(&path, archive).unwrap();
+        let mut reader = ArchiveReader::new(crate::SharedFile::open(&path).unwrap());
+        reader.read_header().unwrap();
+
+        std::thread::scope(|scope| {
+            for (name, expected) in [("a.bin", &a), ("b.bin", &b)] {
+                let reader = &reader;
+                scope.spawn(move || {
+                    let file_entry = reader.get_file_by_path(name).unwrap().unwrap();
+                    let mut file_reader = reader.get_file_reader_owned(file_entry).unwrap();
+
+                    // Read in small chunks so the threads interleave.
+                    let mut data = Vec::new();
+                    let mut buffer = [0; 64];
+                    loop {
+                        let n = file_reader.read(&mut buffer).unwrap();
+                        if n == 0 {
+                            break;
+                        }
+                        data.extend_from_slice(&buffer[..n]);
+                    }
+                    assert!(data == *expected);
+                });
+            }
+        });
+
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_long_file_name() {
+        // A len of 16384 overflows if multiplied by 4 as a u16.
+        let len: u16 = 16384;
+        let mut name = vec![b'A'; usize::from(len) * 4];
+        *name.last_mut().unwrap() = 0;
+        let parity = name
+            .iter()
+            .fold(0_u16, |acc, byte| acc.wrapping_add((*byte).into()));
+
+        let mut data = Vec::new();
+        data.extend(len.to_le_bytes());
+        data.extend(parity.to_le_bytes());
+        data.extend(&name);
+        data.extend(&name);
+
+        let mut reader = ArchiveReader::new_unencrypted(Cursor::new(data));
+        let (upper_file_name, file_name) = reader.read_file_name_data().unwrap();
+        assert!(file_name.len() == usize::from(len) * 4 - 1);
+        assert!(upper_file_name == file_name);
+    }
+
+    #[test]
+    fn max_decompressed_size() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(true);
+        writer.add_file("data.bin", &[0; 1000]).unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive)).with_max_decompressed_size(999);
+        reader.read_header().unwrap();
+        let file_entry = reader.get_file_by_path("data.bin").unwrap().unwrap();
+        assert!(matches!(
+            reader.get_file_reader(file_entry),
+            Err(Error::DecompressedSizeTooLarge {
+                size: 1000,
+                max: 999
+            })
+        ));
+    }
+
+    #[test]
+    fn embedded_archive() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_file("Data/BasicData/Game.dat", b"game data")
+            .unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_reader_buffered(archive.as_slice()).unwrap();
+        reader.read_header().unwrap();
+        assert!(reader.read("Data/BasicData/Game.dat").unwrap() == b"game data");
+
+        // Make the archive straddle a chunk boundary.
+        let offset = 64 * 1024 - 1;
+        let mut exe = vec![0; offset];
+        exe.extend(&archive);
+
+        let mut exe = Cursor::new(exe);
+        assert!(find_archive_offset(&mut exe).unwrap() == Some(u64::try_from(offset).unwrap()));
+        assert!(find_archive_offset(&mut Cursor::new(vec![0; 1000]))
+            .unwrap()
+            .is_none());
+
+        let mut reader = ArchiveReader::new_at(exe, u64::try_from(offset).unwrap());
+        reader.read_header().unwrap();
+
+        let file_entry = reader
+            .get_file_by_path("Data/BasicData/Game.dat")
+            .unwrap()
+            .unwrap();
+        let mut data = Vec::new();
+        reader
+            .get_file_reader(file_entry)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == b"game data");
+
+        data.clear();
+        reader
+            .get_file_slice_reader(file_entry)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == b"game data");
+    }
+
+    #[test]
+    fn embedded_archive_custom_key() {
+        let key_string = KeyString::new(*b"0123456789AB");
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_key_string(key_string);
+        writer.add_file("Config.ini", b"[Config]").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let offset = 1000;
+        let mut exe = vec![0; offset];
+        exe.extend(&archive);
+        let mut exe = Cursor::new(exe);
+
+        // The default key can't find it, but a builder with the right key can.
+        assert!(find_archive_offset(&mut exe).unwrap().is_none());
+        let builder = ArchiveReaderBuilder::new().key_string(key_string);
+        let found_offset = builder.find_archive_offset(&mut exe).unwrap();
+        assert!(found_offset == Some(u64::try_from(offset).unwrap()));
+
+        let mut reader = builder.base_offset(found_offset.unwrap()).build(exe);
+        reader.read_header().unwrap();
+        assert!(reader.read("Config.ini").unwrap() == b"[Config]");
+    }
+
+    #[test]
+    fn invalid_table_positions() {
+        let writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        let archive = writer.finish().unwrap().into_inner();
+
+        // Decode the header, swap the file and directory table positions, and re-encode it.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let mut header = archive[..48].to_vec();
+        key_xor(0, key, &mut header);
+        let (file_table_position, directory_table_position) = header[24..40].split_at_mut(8);
+        file_table_position.swap_with_slice(directory_table_position);
+        key_xor(0, key, &mut header);
+
+        let mut archive = archive;
+        archive[..48].copy_from_slice(&header);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        assert!(matches!(reader.read_header(), Err(Error::InvalidHeader)));
+        assert!(reader.into_inner().into_inner()[..48] == header);
+    }
+
+    #[test]
+    fn directory_cycle() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_dir("A/B").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let file_table_position = reader.file_table_position().unwrap();
+        let header_size = reader.header_size().unwrap();
+
+        // Point the dir entry of "A/B" back at the root dir.
+        // The file table is in breadth-first order, so "A/B" is the third entry.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        let data_position = usize::try_from(file_table_position).unwrap() + (2 * 64) + 40;
+        tables[data_position..data_position + 8].copy_from_slice(&0_u64.to_le_bytes());
+        key_xor(file_name_table_position, key, tables);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+        let root_dir = reader.get_root_dir().unwrap().unwrap();
+        let result: Result<Vec<_>, _> = reader.walk_dir(root_dir).unwrap().collect();
+        assert!(matches!(result, Err(Error::DirectoryCycle)));
+
+        let errors = reader.validate().unwrap_err();
+        assert!(matches!(
+            errors.as_slice(),
+            [ValidationError::InvalidDirectoryPosition { .. }]
+        ));
+    }
+
+    #[test]
+    fn inflated_file_size() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("a.txt", b"a").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let file_table_position = reader.file_table_position().unwrap();
+        let header_size = reader.header_size().unwrap();
+        let position = reader
+            .get_file_by_path("a.txt")
+            .unwrap()
+            .unwrap()
+            .position();
+
+        // Set the size of the file to something that can't be allocated.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        let data_size_position = usize::try_from(file_table_position + position).unwrap() + 48;
+        tables[data_size_position..data_size_position + 8]
+            .copy_from_slice(&(1_u64 << 60).to_le_bytes());
+        key_xor(file_name_table_position, key, tables);
+
+        // Reading stops at the end of the archive, instead of allocating the whole size up front.
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let data = reader.read("a.txt").unwrap();
+        assert!(data.len() <= archive.len());
+    }
+
+    #[test]
+    fn truncated_compressed_file() {
+        let data = [b'a'; 1000];
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(true);
+        writer.add_file("a.txt", &data).unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let file_table_position = reader.file_table_position().unwrap();
+        let header_size = reader.header_size().unwrap();
+        let file_entry = reader.get_file_by_path("a.txt").unwrap().unwrap();
+        assert!(file_entry.is_compressed());
+        let position = file_entry.position();
+        let data_position = reader.data_position().unwrap() + file_entry.data_position;
+
+        // Make the compressed data run past the end of the archive.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        let compressed_size_position =
+            usize::try_from(file_table_position + position).unwrap() + 56;
+        tables[compressed_size_position..compressed_size_position + 8]
+            .copy_from_slice(&(1_u64 << 40).to_le_bytes());
+        key_xor(file_name_table_position, key, tables);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+        let file_entry = reader.get_file_by_path("a.txt").unwrap().unwrap();
+        assert!(matches!(
+            reader.get_file_reader(file_entry),
+            Err(Error::IoAt { offset, .. }) if offset == data_position
+        ));
+        assert!(matches!(
+            reader.read_raw_file_data(file_entry),
+            Err(Error::IoAt { offset, .. }) if offset == data_position
+        ));
+    }
+
+    #[test]
+    fn stats_overflow() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("a.txt", b"a").unwrap();
+        writer.add_file("b.txt", b"b").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let file_table_position = reader.file_table_position().unwrap();
+        let header_size = reader.header_size().unwrap();
+        let positions: Vec<_> = ["a.txt", "b.txt"]
+            .into_iter()
+            .map(|path| reader.get_file_by_path(path).unwrap().unwrap().position())
+            .collect();
+
+        // Give both files the largest possible size, so their total overflows.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        for position in positions {
+            let data_size_position = usize::try_from(file_table_position + position).unwrap() + 48;
+            tables[data_size_position..data_size_position + 8]
+                .copy_from_slice(&u64::MAX.to_le_bytes());
+        }
+        key_xor(file_name_table_position, key, tables);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+        assert!(matches!(reader.stats(), Err(Error::IntegerOverflow)));
+    }
+
+    #[test]
+    fn file_name_case_mismatch() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("a.txt", b"a").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let header_size = reader.header_size().unwrap();
+
+        // Change the file name, but not the upper-case file name, which the parity is checked against.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        let name_position = tables
+            .windows(5)
+            .position(|window| window == b"a.txt")
+            .unwrap();
+        tables[name_position] = b'b';
+        key_xor(file_name_table_position, key, tables);
+
+        // Names are only checked if asked to.
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        assert!(reader.exists("b.txt").unwrap());
+
+        let mut reader = ArchiveReader::from_bytes(archive).strict_names(true);
+        assert!(matches!(
+            reader.read_header(),
+            Err(Error::FilenameCaseMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn inflated_num_files() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("a.txt", b"a").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let file_name_table_position = reader.file_name_table_position().unwrap();
+        let directory_table_position = reader.directory_table_position().unwrap();
+
+        // Set the file count of the root dir, the first dir entry, to something huge.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let num_files_position =
+            usize::try_from(file_name_table_position + directory_table_position).unwrap() + 16;
+        let num_files = &mut archive[num_files_position..num_files_position + 8];
+        key_xor(u64::try_from(num_files_position).unwrap(), key, num_files);
+        num_files.copy_from_slice(&1_000_000_u64.to_le_bytes());
+        key_xor(u64::try_from(num_files_position).unwrap(), key, num_files);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        assert!(matches!(reader.read_header(), Err(Error::InvalidHeader)));
+    }
+
+    #[test]
+    fn empty_directory_table() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("a.txt", b"a").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive.clone());
+        reader.read_header().unwrap();
+        let directory_table_position = reader.directory_table_position().unwrap();
+
+        // Shrink the header so that it ends where the directory table starts.
+        let key = create_key(DEFAULT_KEY_STRING);
+        let header_size = &mut archive[4..8];
+        key_xor(4, key, header_size);
+        header_size.copy_from_slice(
+            &u32::try_from(directory_table_position)
+                .unwrap()
+                .to_le_bytes(),
+        );
+        key_xor(4, key, header_size);
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+        assert!(reader.get_root_dir().unwrap().is_none());
+        assert!(reader.walk().unwrap().next().is_none());
+        assert!(reader.files().unwrap().count() == 2);
+        assert!(reader.get_file_by_path("a.txt").unwrap().is_none());
+    }
+
+    #[test]
+    fn extract_all_path_collision() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("Foo.png", b"upper").unwrap();
+        writer.add_file("foo.png", b"lower").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+
+        let dest = std::env::temp_dir().join("wolf-rpg-data-extract-all-path-collision");
+        let result = reader.extract_all(&dest);
+        std::fs::remove_dir_all(&dest).unwrap();
+        assert!(matches!(result, Err(Error::PathCollision { .. })));
+    }
+
+    #[test]
+    fn cancel_and_progress() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        writer.add_file("A.txt", b"a").unwrap();
+        writer.add_file("B.txt", b"b").unwrap();
+        writer.add_file("C.txt", b"c").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+
+        let visited = std::cell::Cell::new(0);
+        let result = reader.walk_with(
+            || visited.get() == 2,
+            |_entry| {
+                visited.set(visited.get() + 1);
+                Ok(())
+            },
+        );
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(visited.get() == 2);
+
+        let mut progress = Vec::new();
+        let dest = std::env::temp_dir().join("wolf-rpg-data-progress");
+        reader
+            .extract_all_with_progress(&dest, |path, done, total| {
+                progress.push((path.strip_prefix(&dest).unwrap().to_path_buf(), done, total));
+            })
+            .unwrap();
+        std::fs::remove_dir_all(&dest).unwrap();
+        assert!(
+            progress
+                == [
+                    (PathBuf::from("A.txt"), 1, 3),
+                    (PathBuf::from("B.txt"), 2, 3),
+                    (PathBuf::from("C.txt"), 3, 3),
+                ]
+        );
+
+        let cancelled = std::sync::atomic::AtomicBool::new(true);
+        let dest = std::env::temp_dir().join("wolf-rpg-data-cancel");
+        let result = reader.extract_all_cancellable(&dest, || cancelled.load(Ordering::Relaxed));
+        let extracted = dest.join("A.txt").exists();
+        std::fs::remove_dir_all(&dest).unwrap();
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(!extracted);
+    }
+
+    #[test]
+    fn archive_reader_is_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<ArchiveReader<File>>();
+        assert_send_sync::<ArchiveReader<Cursor<Vec<u8>>>>();
+    }
+
+    /// Make a reader for an archive with UTF-8 file names, containing `Données/café.txt`.
+    fn utf8_archive() -> ArchiveReader<Cursor<Vec<u8>>> {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()))
+            .with_code_page(65001)
+            .unwrap();
+        writer.add_file("Données/café.txt", b"UTF-8").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive));
+        reader.read_header().unwrap();
+        reader.validate().unwrap();
+
+        reader
+    }
+
+    #[test]
+    fn walk_utf8_file_names() {
+        let reader = utf8_archive();
+        let root_dir = reader.get_root_dir().unwrap().unwrap();
+
+        let paths: Vec<_> = reader
+            .walk_dir(root_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path_components().join("/"))
+            .collect();
+        assert!(paths == ["", "Données", "Données/café.txt"]);
+        let walk_paths: Vec<_> = reader
+            .walk()
+            .unwrap()
+            .map(|entry| entry.unwrap().path_components().join("/"))
+            .collect();
+        assert!(walk_paths == paths);
+
+        let paths: Vec<_> = reader
+            .walk_dir_with_depth(root_dir, 1)
+            .unwrap()
+            .map(|entry| entry.unwrap().path_components().join("/"))
+            .collect();
+        assert!(paths == ["", "Données"]);
+
+        let paths: Vec<_> = reader
+            .walk_dir(root_dir)
+            .unwrap()
+            .files_only()
+            .map(|entry| entry.unwrap().path_components().join("/"))
+            .collect();
+        assert!(paths == ["Données/café.txt"]);
+        let paths: Vec<_> = reader
+            .walk_dir(root_dir)
+            .unwrap()
+            .dirs_only()
+            .map(|entry| entry.unwrap().path_components().join("/"))
+            .collect();
+        assert!(paths == ["", "Données"]);
+    }
+
+    #[test]
+    fn navigate_utf8_dirs() {
+        let reader = utf8_archive();
+        let root_dir = reader.get_root_dir().unwrap().unwrap();
+
+        let dir = reader
+            .get_dir_from_file(reader.get_file_by_path("Données").unwrap().unwrap())
+            .unwrap();
+        assert!(reader.dir_path(dir).unwrap() == Path::new("Données"));
+        assert!(reader.dir_path(root_dir).unwrap() == Path::new(""));
+        assert!(root_dir.parent_directory_position().is_none());
+        assert!(dir.parent_directory_position() == Some(0));
+        assert!(dir.num_files() == 1);
+        assert!(reader
+            .get_dir_file(dir, 0)
+            .unwrap()
+            .is_some_and(|file_entry| reader.get_file_name(file_entry).unwrap() == "café.txt"));
+        assert!(dir.file_head_position() > dir.directory_position());
+        let children: Vec<_> = reader
+            .read_dir(root_dir)
+            .map(|file_entry| reader.get_file_name(file_entry.unwrap()).unwrap())
+            .collect();
+        assert!(children == ["Données"]);
+        let children: Vec<_> = reader
+            .get_children(dir)
+            .unwrap()
+            .map(|file_entry| reader.get_file_name(file_entry).unwrap())
+            .collect();
+        assert!(children == ["café.txt"]);
+        let file_entry = reader.get_dir_file(dir, 0).unwrap().unwrap();
+        assert!(reader
+            .parent_of_file(file_entry)
+            .unwrap()
+            .is_some_and(|parent| parent.directory_position() == dir.directory_position()));
+        assert!(reader
+            .parent_of_file(reader.get_file_from_dir(dir).unwrap())
+            .unwrap()
+            .is_some_and(|parent| parent.directory_position() == root_dir.directory_position()));
+        assert!(reader
+            .parent_of_file(reader.get_file_from_dir(root_dir).unwrap())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn get_file_by_path() {
+        let reader = utf8_archive();
+
+        let file_entry = reader.get_file_by_path("Données/café.txt").unwrap();
+        assert!(file_entry.is_some_and(|file_entry| file_entry.size() == 5));
+        assert!(reader
+            .get_file_by_path("Données")
+            .unwrap()
+            .is_some_and(|file_entry| file_entry.is_dir()));
+        assert!(reader
+            .get_file_by_path("Données/cafe.txt")
+            .unwrap()
+            .is_none());
+        assert!(reader
+            .get_file_by_path("DONNéES/CAFé.TXT")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn get_file_by_path_ci() {
+        let reader = utf8_archive();
+
+        for path in ["Données/café.txt", "DONNéES/CAFé.TXT", "données/Café.Txt"] {
+            assert!(reader
+                .get_file_by_path_ci(path)
+                .unwrap()
+                .is_some_and(|file_entry| file_entry.size() == 5));
+        }
+
+        // Only ASCII case is ignored.
+        assert!(reader
+            .get_file_by_path_ci("DONNÉES/CAFÉ.TXT")
+            .unwrap()
+            .is_none());
+        assert!(reader
+            .get_file_by_path_ci("Données/cafe.txt")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn exists() {
+        let reader = utf8_archive();
+
+        assert!(reader.exists("Données").unwrap());
+        assert!(reader.exists("Données/café.txt").unwrap());
+        assert!(!reader.exists("Données/cafe.txt").unwrap());
+        assert!(!reader.exists("Missing").unwrap());
+    }
+
+    #[test]
+    fn metadata() {
+        let reader = utf8_archive();
+
+        assert!(reader
+            .metadata("Données/café.txt")
+            .unwrap()
+            .is_some_and(|file_entry| !file_entry.is_dir() && file_entry.size() == 5));
+        assert!(reader
+            .metadata("Données")
+            .unwrap()
+            .is_some_and(|file_entry| file_entry.is_dir()));
+        assert!(reader.metadata("Données/cafe.txt").unwrap().is_none());
+    }
+
+    #[test]
+    fn find() {
+        let reader = utf8_archive();
+
+        let found = reader.find(|name, _| name.ends_with(".txt")).unwrap();
+        assert!(found.len() == 1 && found[0].size() == 5);
+
+        // The root dir has no name, so it is never passed to the predicate.
+        let found = reader.find(|_, file_entry| file_entry.is_dir()).unwrap();
+        assert!(found.len() == 1);
+        assert!(reader.find(|_, _| false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stats() {
+        let reader = utf8_archive();
+
+        let stats = reader.stats().unwrap();
+        assert!(
+            stats
+                == ArchiveStats {
+                    file_count: 1,
+                    dir_count: 1,
+                    uncompressed_size: 5,
+                    compressed_size: 5,
+                }
+        );
+    }
+
+    #[test]
+    fn read_by_path() {
+        let reader = utf8_archive();
+
+        let mut data = Vec::new();
+        reader
+            .get_file_reader_by_path("Données/café.txt")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == b"UTF-8");
+        assert!(reader.read("Données/café.txt").unwrap() == b"UTF-8");
+        assert!(reader.read_to_string("Données/café.txt").unwrap() == "UTF-8");
+
+        let mut out = Vec::new();
+        assert!(
+            reader
+                .extract_file_to("Données/café.txt", &mut out)
+                .unwrap()
+                == 5
+        );
+        assert!(out == b"UTF-8");
+
+        assert!(matches!(
+            reader.get_file_reader_by_path("Données"),
+            Err(Error::NotAFile)
+        ));
+        assert!(matches!(
+            reader.get_file_reader_by_path("Données/cafe.txt"),
+            Err(Error::NotFound { .. })
+        ));
+        assert!(matches!(
+            reader.read("Données/cafe.txt"),
+            Err(Error::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn build_path_index() {
+        let reader = utf8_archive();
+
+        let index = reader.build_path_index().unwrap();
+        let mut paths: Vec<_> = index.keys().map(String::as_str).collect();
+        paths.sort();
+        assert!(paths == ["Données", "Données/café.txt"]);
+        assert!(index["Données"].is_dir());
+        assert!(index["Données/café.txt"].size() == 5);
+
+        let reader = ArchiveReader::new(Cursor::new(Vec::<u8>::new()));
+        assert!(matches!(
+            reader.build_path_index(),
+            Err(Error::HeaderNotRead)
+        ));
+    }
+
+    #[test]
+    fn read_file_chunks() {
+        // Large enough to take more than one chunk.
+        let data: Vec<u8> = (0..=u8::MAX).cycle().take(200_000).collect();
+
+        for compression in [false, true] {
+            let mut writer =
+                ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(compression);
+            writer.add_file("data.bin", &data).unwrap();
+            let archive = writer.finish().unwrap().into_inner();
+
+            let mut reader = ArchiveReader::new(Cursor::new(archive));
+            reader.read_header().unwrap();
+            let file_entry = reader.get_file_by_path("data.bin").unwrap().unwrap();
+            assert!(file_entry.is_compressed() == compression);
+
+            let mut chunks = Vec::new();
+            reader
+                .read_file_chunks(file_entry, |chunk| {
+                    chunks.push(chunk.to_vec());
+                    Ok(())
+                })
+                .unwrap();
+            assert!(chunks.len() > 1);
+            assert!(chunks.concat() == data);
+
+            // Errors from the callback stop the read.
+            let mut num_chunks = 0;
+            let result = reader.read_file_chunks(file_entry, |_chunk| {
+                num_chunks += 1;
+                Err(std::io::ErrorKind::Other.into())
+            });
+            assert!(matches!(result, Err(Error::Io(_))));
+            assert!(num_chunks == 1);
+        }
+    }
+
+    #[test]
+    fn encoding_override() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()))
+            .with_code_page(65001)
+            .unwrap();
+        writer.add_file("café.txt", b"UTF-8").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        // The header is XOR-ed with the key, so XOR-ing the plain values swaps the code page.
+        for (byte, diff) in archive[40..48]
+            .iter_mut()
+            .zip((65001_u64 ^ 1234).to_le_bytes())
+        {
+            *byte ^= diff;
+        }
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive.clone()));
+        assert!(matches!(
+            reader.read_header(),
+            Err(Error::UnknownCodePage { code_page: 1234 })
+        ));
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive)).with_encoding(encoding_rs::UTF_8);
+        reader.read_header().unwrap();
+        assert!(reader.code_page().unwrap() == 1234);
+        assert!(reader.encoding() == encoding_rs::UTF_8);
+        assert!(reader.encoding_name() == "UTF-8");
+
+        assert!(reader.position() != 0);
+        reader.reset().unwrap();
+        assert!(reader.position() == 0);
+        assert!(matches!(reader.code_page(), Err(Error::HeaderNotRead)));
+        reader.read_header().unwrap();
+        assert!(reader.code_page().unwrap() == 1234);
+        assert!(reader.get_file_by_path("café.txt").unwrap().is_some());
+    }
+
+    #[test]
+    fn lossy_names() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()))
+            .with_code_page(65001)
+            .unwrap();
+        writer.add_file("café.txt", b"UTF-8").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        // Replace the "é" in both names with an invalid byte,
+        // keeping the byte sum the same so the parity check still passes.
+        let mut decoded = archive.clone();
+        key_xor(0, create_key(DEFAULT_KEY_STRING), &mut decoded);
+        let positions: Vec<_> = decoded
+            .windows(2)
+            .enumerate()
+            .filter(|(_, window)| *window == [0xC3, 0xA9])
+            .map(|(position, _)| position)
+            .collect();
+        assert!(positions.len() == 2);
+
+        // Corrupting only the upper-case name breaks the parity check,
+        // which reports the offset of the start of the name.
+        let mut bad_parity_archive = archive.clone();
+        bad_parity_archive[positions[0]] ^= 0x01;
+        let mut reader = ArchiveReader::new(Cursor::new(bad_parity_archive));
+        let Err(Error::InvalidFileNameParity { offset }) = reader.read_header() else {
+            panic!("expected a parity error");
+        };
+        // The upper-case name comes right after the u16 length and parity, and "CAF" comes before the "é".
+        assert!(offset == u64::try_from(positions[0] - 4 - 3).unwrap());
+
+        for position in positions {
+            archive[position] ^= 0xC3 ^ 0xFF;
+            archive[position + 1] ^= 0xA9 ^ 0x6D;
+        }
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive.clone()));
+        assert!(matches!(reader.read_header(), Err(Error::InvalidFileName)));
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive)).lossy_names(true);
+        reader.read_header().unwrap();
+        assert!(reader
+            .get_file_by_path("caf\u{FFFD}m.txt")
+            .unwrap()
+            .is_some());
+    }
+
+    /// Add a name to a version 8 file name table, returning its position.
+    fn encode_name_v8(names: &mut Vec<u8>, name: &str) -> u64 {
+        let position = u64::try_from(names.len()).unwrap();
+
+        // Names are padded with at least one NUL, except for the empty root dir name.
+        let len = if name.is_empty() {
+            0
+        } else {
+            name.len() / 4 + 1
+        };
+        let mut upper = name.to_ascii_uppercase().into_bytes();
+        upper.resize(len * 4, 0);
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(len * 4, 0);
+        let parity = upper
+            .iter()
+            .fold(0_u16, |acc, byte| acc.wrapping_add((*byte).into()));
+
+        names.extend(u16::try_from(len).unwrap().to_le_bytes());
+        names.extend(parity.to_le_bytes());
+        names.extend(upper);
+        names.extend(bytes);
+
+        position
+    }
+
+    /// Pack file data like a version 8 archive with a `HuffmanEncodeKB` of 1,
+    /// returning the stored data and the compressed and Huffman-coded sizes.
+    ///
+    /// The key path is the upper-case names of the file and its parent dirs, from the innermost out.
+    fn pack_file_v8(
+        contents: &[u8],
+        key_path: Option<&str>,
+        compress: bool,
+        huffman: bool,
+    ) -> (Vec<u8>, u64, u64) {
+        const END_SIZE: usize = 1024;
+
+        let mut stored = if compress {
+            compress_file_data(contents)
+        } else {
+            contents.to_vec()
+        };
+        let compressed_size = if compress {
+            u64::try_from(stored.len()).unwrap()
+        } else {
+            u64::MAX
+        };
+
+        let mut huffman_size = u64::MAX;
+        if huffman {
+            let packed = stored;
+            if packed.len() > END_SIZE * 2 {
+                let middle = &packed[END_SIZE..(packed.len() - END_SIZE)];
+                let ends = [&packed[..END_SIZE], &packed[(packed.len() - END_SIZE)..]].concat();
+                stored = huffman_encode(&ends);
+                huffman_size = u64::try_from(stored.len()).unwrap();
+                stored.extend(middle);
+            } else {
+                stored = huffman_encode(&packed);
+                huffman_size = u64::try_from(stored.len()).unwrap();
+            }
+        }
+
+        if let Some(key_path) = key_path {
+            let mut key_source = DEFAULT_KEY_STRING.as_bytes().to_vec();
+            key_source.extend(key_path.as_bytes());
+            let size = u64::try_from(contents.len()).unwrap();
+            key_xor(size, create_key_v8(&key_source), &mut stored);
+        }
+
+        (stored, compressed_size, huffman_size)
+    }
+
+    /// Make a version 8 archive with the default key string and the given header flags,
+    /// returning it along with the path and contents of each file.
+    ///
+    /// This follows the steps of DxLib's encoder, in the reverse order of how they are read.
+    fn make_v8_archive(flags: u32) -> (Vec<u8>, Vec<(&'static str, Vec<u8>)>) {
+        let has_key = flags & 0x1 == 0;
+        let compress_tables = flags & 0x2 == 0;
+
+        let mut state = 0x1234_5678_u32;
+        let big: Vec<u8> = (0..5000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                u8::try_from(state % 16).unwrap()
+            })
+            .collect();
+        let files = vec![
+            ("Config.ini", b"[Config]\r\nTitle=Test\r\n".to_vec()),
+            ("Data/Big.dat", big),
+            ("Data/Raw.bin", (0..=u8::MAX).cycle().take(300).collect()),
+        ];
+
+        let mut names = Vec::new();
+        let mut data = Vec::new();
+        let mut file_table = Vec::new();
+        let mut encode_file =
+            |name_position: u64, attributes: Attributes, data_position: u64, sizes: [u64; 3]| {
+                for value in [name_position, attributes.bits(), 0, 0, 0, data_position]
+                    .into_iter()
+                    .chain(sizes)
+                {
+                    file_table.extend(value.to_le_bytes());
+                }
+            };
+        let add_file = |names: &mut Vec<u8>,
+                        data: &mut Vec<u8>,
+                        name: &str,
+                        contents: &[u8],
+                        key_path: &str,
+                        compress: bool,
+                        huffman: bool| {
+            let name_position = encode_name_v8(names, name);
+            let key_path = Some(key_path).filter(|_| has_key);
+            let (stored, compressed_size, huffman_size) =
+                pack_file_v8(contents, key_path, compress, huffman);
+            let data_position = u64::try_from(data.len()).unwrap();
+            data.extend(stored);
+
+            let size = u64::try_from(contents.len()).unwrap();
+            (
+                name_position,
+                data_position,
+                [size, compressed_size, huffman_size],
+            )
+        };
+        let no_data = [0, u64::MAX, u64::MAX];
+
+        let root_name = encode_name_v8(&mut names, "");
+        encode_file(root_name, Attributes::Directory, 0, no_data);
+        let (name, position, sizes) = add_file(
+            &mut names,
+            &mut data,
+            "Config.ini",
+            &files[0].1,
+            "CONFIG.INI",
+            false,
+            true,
+        );
+        encode_file(name, Attributes::Archive, position, sizes);
+        let data_name = encode_name_v8(&mut names, "Data");
+        encode_file(data_name, Attributes::Directory, 32, no_data);
+        let (name, position, sizes) = add_file(
+            &mut names,
+            &mut data,
+            "Big.dat",
+            &files[1].1,
+            "BIG.DATDATA",
+            true,
+            true,
+        );
+        encode_file(name, Attributes::Archive, position, sizes);
+        let (name, position, sizes) = add_file(
+            &mut names,
+            &mut data,
+            "Raw.bin",
+            &files[2].1,
+            "RAW.BINDATA",
+            false,
+            false,
+        );
+        encode_file(name, Attributes::Archive, position, sizes);
+
+        let mut tables = names;
+        let file_table_position = u64::try_from(tables.len()).unwrap();
+        tables.extend(file_table);
+        let directory_table_position = u64::try_from(tables.len()).unwrap();
+        for value in [0, u64::MAX, 2, 72, 144, 0, 2, 216] {
+            tables.extend(value.to_le_bytes());
+        }
+        let header_size = u32::try_from(tables.len()).unwrap();
+
+        if has_key {
+            key_xor(0, create_key_v8(DEFAULT_KEY_STRING.as_bytes()), &mut tables);
+        }
+        if compress_tables {
+            tables = huffman_encode(&compress_file_data(&tables));
+        }
+
+        let mut archive = Vec::new();
+        archive.extend(b"DX");
+        archive.extend(8_u16.to_le_bytes());
+        archive.extend(header_size.to_le_bytes());
+        archive.extend(HEADER_SIZE_V8.to_le_bytes());
+        archive.extend((HEADER_SIZE_V8 + u64::try_from(data.len()).unwrap()).to_le_bytes());
+        archive.extend(file_table_position.to_le_bytes());
+        archive.extend(directory_table_position.to_le_bytes());
+        archive.extend(932_u32.to_le_bytes());
+        archive.extend(flags.to_le_bytes());
+        archive.push(1);
+        archive.extend([0; 15]);
+        archive.extend(data);
+        archive.extend(tables);
+
+        (archive, files)
+    }
+
+    #[test]
+    fn read_v8_archive() {
+        // Flags: none, no key, and uncompressed tables.
+        for flags in [0x0, 0x1, 0x2] {
+            let (archive, files) = make_v8_archive(flags);
+
+            let mut reader = ArchiveReader::from_bytes(archive);
+            reader.read_header().unwrap();
+            assert!(reader.version().unwrap() == 8);
+            reader.validate().unwrap();
+
+            for (path, contents) in files.iter() {
+                assert!(reader.read(path).unwrap() == *contents, "{path}");
+
+                let file_entry = reader.get_file_by_path(path).unwrap().unwrap();
+                let mut data = Vec::new();
+                reader
+                    .get_file_slice_reader(file_entry)
+                    .unwrap()
+                    .read_to_end(&mut data)
+                    .unwrap();
+                assert!(data == *contents, "{path}");
+            }
+
+            // Only the ends of the big file are Huffman-coded, and it stays compressed when read raw.
+            let big = reader.get_file_by_path("Data/Big.dat").unwrap().unwrap();
+            assert!(big.huffman_size().is_some());
+            let raw_data = reader.read_raw_file_data(big).unwrap();
+            assert!(u64::try_from(raw_data.len()).ok() == big.compressed_size());
+            assert!(decompress_file_data(&raw_data, big.size()).unwrap() == files[1].1);
+
+            // The stored sizes cover all of the file data, which comes right before the tables.
+            let stats = reader.stats().unwrap();
+            assert!(stats.file_count == 3);
+            assert!(
+                stats.compressed_size
+                    == reader.file_name_table_position().unwrap() - reader.data_position().unwrap()
+            );
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use super::find_archive_offset_with_key;
+use super::ArchiveReader;
+use crate::create_key;
+use crate::Error;
+use crate::KeyString;
+use crate::DEFAULT_KEY_STRING;
+use crate::KEY_LEN;
+use encoding_rs::SHIFT_JIS;
+use std::io::Read;
+use std::io::Seek;
+use std::sync::atomic::AtomicU64;
+use std::sync::RwLock;
+
+/// A builder for an [`ArchiveReader`], for combining options.
+///
+/// The same builder may be used to create multiple readers.
+#[derive(Debug, Clone)]
+pub struct ArchiveReaderBuilder {
+    /// The key string, or `None` for unencrypted archives.
+    key_string: Option<KeyString>,
+    base_offset: u64,
+    encoding: Option<&'static encoding_rs::Encoding>,
+    lossy_names: bool,
+    strict_names: bool,
+    max_decompressed_size: Option<u64>,
+}
+
+impl ArchiveReaderBuilder {
+    /// Make a new builder with the default options.
+    pub fn new() -> Self {
+        Self {
+            key_string: Some(DEFAULT_KEY_STRING),
+            base_offset: 0,
+            encoding: None,
+            lossy_names: false,
+            strict_names: false,
+            max_decompressed_size: None,
+        }
+    }
+
+    /// Use a custom key string.
+    ///
+    /// This overrides [`ArchiveReaderBuilder::unencrypted`].
+    pub fn key_string(mut self, key_string: KeyString) -> Self {
+        self.key_string = Some(key_string);
+        self
+    }
+
+    /// Read an archive that is not encrypted.
+    ///
+    /// This overrides [`ArchiveReaderBuilder::key_string`].
+    pub fn unencrypted(mut self) -> Self {
+        self.key_string = None;
+        self
+    }
+
+    /// Set the offset of the archive in the reader.
+    ///
+    /// See [`ArchiveReader::new_at`].
+    pub fn base_offset(mut self, base_offset: u64) -> Self {
+        self.base_offset = base_offset;
+        self
+    }
+
+    /// Set the encoding used for file names, ignoring the code page in the header.
+    ///
+    /// See [`ArchiveReader::with_encoding`].
+    pub fn encoding(mut self, encoding: &'static encoding_rs::Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// Set whether malformed file names are decoded lossily.
+    ///
+    /// See [`ArchiveReader::lossy_names`].
+    pub fn lossy_names(mut self, lossy_names: bool) -> Self {
+        self.lossy_names = lossy_names;
+        self
+    }
+
+    /// Set whether file names are checked against their upper-case names.
+    ///
+    /// See [`ArchiveReader::strict_names`].
+    pub fn strict_names(mut self, strict_names: bool) -> Self {
+        self.strict_names = strict_names;
+        self
+    }
+
+    /// Set the maximum size of a compressed file, before and after decompression.
+    ///
+    /// See [`ArchiveReader::with_max_decompressed_size`].
+    pub fn max_decompressed_size(mut self, max_decompressed_size: u64) -> Self {
+        self.max_decompressed_size = Some(max_decompressed_size);
+        self
+    }
+
+    /// Find the offset of an archive embedded in another file, like an executable, using the key of this builder.
+    ///
+    /// The offset of this builder is ignored, as the whole reader is scanned.
+    /// See [`find_archive_offset`](crate::find_archive_offset).
+    pub fn find_archive_offset<R>(&self, reader: &mut R) -> Result<Option<u64>, Error>
+    where
+        R: Read + Seek,
+    {
+        find_archive_offset_with_key(reader, self.key_string.map_or([0; KEY_LEN], create_key))
+    }
+
+    /// Create an archive reader with these options.
+    ///
+    /// The header still needs to be read.
+    pub fn build<R>(&self, reader: R) -> ArchiveReader<R> {
+        ArchiveReader {
+            reader: RwLock::new(reader),
+            position: AtomicU64::new(0),
+            // XOR-ing with an all-zero key is a no-op.
+            key: self.key_string.map_or([0; KEY_LEN], create_key),
+            key_string: self.key_string,
+
+            base_offset: self.base_offset,
+
+            encoding: SHIFT_JIS,
+            header_data: None,
+            encoding_override: self.encoding,
+            lossy_names: self.lossy_names,
+            strict_names: self.strict_names,
+
+            max_decompressed_size: self.max_decompressed_size,
+        }
+    }
+}
+
+impl Default for ArchiveReaderBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,16 +3,26 @@ use std::time::UNIX_EPOCH;
 
 /// A file entry
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FileEntry {
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) position: u64,
     pub(super) name_position: u64,
     pub(super) attributes: Attributes,
     pub(super) file_times: FileTimes,
     pub(super) data_position: u64,
     pub(super) data_size: u64,
     pub(super) compressed_data_size: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub(super) huffman_data_size: Option<u64>,
 }
 
 impl FileEntry {
+    /// Get the position of this entry, relative to the file table.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
     /// Returns true if this is for a directory.
     pub fn is_dir(&self) -> bool {
         self.attributes.contains(Attributes::Directory)
@@ -23,6 +33,21 @@ impl FileEntry {
         !self.is_dir()
     }
 
+    /// Returns true if this is marked as read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.attributes.contains(Attributes::ReadOnly)
+    }
+
+    /// Returns true if this is marked as hidden.
+    pub fn is_hidden(&self) -> bool {
+        self.attributes.contains(Attributes::Hidden)
+    }
+
+    /// Returns true if this is marked as a system file.
+    pub fn is_system(&self) -> bool {
+        self.attributes.contains(Attributes::System)
+    }
+
     /// Returns true if this is compressed.
     pub fn is_compressed(&self) -> bool {
         self.compressed_data_size.is_some()
@@ -38,6 +63,13 @@ impl FileEntry {
         self.compressed_data_size
     }
 
+    /// Get the Huffman-coded size, if it is Huffman-coded.
+    ///
+    /// Only files in version 8 archives may be Huffman-coded.
+    pub fn huffman_size(&self) -> Option<u64> {
+        self.huffman_data_size
+    }
+
     /// Get the file times.
     pub fn file_times(&self) -> FileTimes {
         self.file_times
@@ -51,21 +83,30 @@ impl FileEntry {
 
 bitflags::bitflags! {
     #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    #[cfg_attr(feature = "serde", serde(transparent))]
     pub struct Attributes: u64 {
+        const ReadOnly = 0x0001;
+        const Hidden = 0x0002;
+        const System = 0x0004;
         const Directory = 0x0010;
         const Archive = 0x0020;
     }
 }
 
-const FILE_TIME_TO_UNIX_EPOCH_DIFF: u64 = 11_644_473_600_000_000;
+/// The number of nanoseconds between 1601-01-01 and the Unix epoch.
+const FILE_TIME_TO_UNIX_EPOCH_DIFF: u64 = 11_644_473_600_000_000_000;
 const NANOS_PER_SEC: u64 = 1_000_000_000;
 
 /// File times
-#[derive(Debug, Copy, Clone)]
+///
+/// These are stored as Windows FILETIMEs, the number of 100 nanosecond intervals since 1601-01-01.
+#[derive(Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FileTimes {
-    pub(super) created: u64,
-    pub(super) accessed: u64,
-    pub(super) modified: u64,
+    pub(crate) created: u64,
+    pub(crate) accessed: u64,
+    pub(crate) modified: u64,
 }
 
 impl FileTimes {
@@ -84,6 +125,45 @@ impl FileTimes {
         file_time_to_system_time(self.modified)
     }
 
+    /// Get the time this was created, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::created`], this is never lost to conversion.
+    pub fn raw_created(&self) -> u64 {
+        self.created
+    }
+
+    /// Get the time this was accessed, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::accessed`], this is never lost to conversion.
+    pub fn raw_accessed(&self) -> u64 {
+        self.accessed
+    }
+
+    /// Get the time this was modified, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::modified`], this is never lost to conversion.
+    pub fn raw_modified(&self) -> u64 {
+        self.modified
+    }
+
+    /// Get the time this was created, as a UTC date and time.
+    #[cfg(feature = "chrono")]
+    pub fn created_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.created().map(chrono::DateTime::from)
+    }
+
+    /// Get the time this was accessed, as a UTC date and time.
+    #[cfg(feature = "chrono")]
+    pub fn accessed_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.accessed().map(chrono::DateTime::from)
+    }
+
+    /// Get the time this was modified, as a UTC date and time.
+    #[cfg(feature = "chrono")]
+    pub fn modified_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.modified().map(chrono::DateTime::from)
+    }
+
     /// Set the time this was created.
     ///
     /// Returns
@@ -110,6 +190,27 @@ impl FileTimes {
         self.modified = system_time_to_file_time(system_time)?;
         Some(())
     }
+
+    /// Set the time this was created, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::set_created`], this stores the value exactly.
+    pub fn set_raw_created(&mut self, file_time: u64) {
+        self.created = file_time;
+    }
+
+    /// Set the time this was accessed, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::set_accessed`], this stores the value exactly.
+    pub fn set_raw_accessed(&mut self, file_time: u64) {
+        self.accessed = file_time;
+    }
+
+    /// Set the time this was modified, as a raw FILETIME.
+    ///
+    /// Unlike [`FileTimes::set_modified`], this stores the value exactly.
+    pub fn set_raw_modified(&mut self, file_time: u64) {
+        self.modified = file_time;
+    }
 }
 
 fn file_time_to_system_time(file_time: u64) -> Option<SystemTime> {
@@ -134,3 +235,29 @@ fn system_time_to_file_time(system_time: SystemTime) -> Option<u64> {
 
     Some(filetime_100_nanos)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_time_conversion() {
+        // 2024-01-01T00:00:00Z
+        let file_time = 133_485_408_000_000_000;
+        let system_time = UNIX_EPOCH + std::time::Duration::from_secs(1_704_067_200);
+
+        assert!(file_time_to_system_time(file_time) == Some(system_time));
+        assert!(system_time_to_file_time(system_time) == Some(file_time));
+        assert!(file_time_to_system_time(0).is_none());
+
+        let mut file_times = FileTimes::default();
+        file_times.set_modified(system_time).unwrap();
+        assert!(file_times.raw_modified() == file_time);
+        assert!(file_times.raw_created() == 0);
+
+        // Values that can't be converted to a system time are still kept.
+        file_times.set_raw_accessed(1);
+        assert!(file_times.accessed().is_none());
+        assert!(file_times.raw_accessed() == 1);
+    }
+}
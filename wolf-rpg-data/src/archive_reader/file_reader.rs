@@ -1,6 +1,13 @@
-use super::key_xor;
+use crate::key_xor;
+use crate::Error;
 use crate::Key;
 use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::RwLockReadGuard;
+use std::sync::RwLockWriteGuard;
 
 /// A reader for files
 #[derive(Debug)]
@@ -8,43 +15,156 @@ pub struct FileReader<'a, R> {
     pub(super) inner: FileReaderInner<'a, R>,
 }
 
+impl<R> FileReader<'_, R> {
+    /// Get the size of the file, after decompression.
+    pub fn len(&self) -> u64 {
+        match &self.inner {
+            FileReaderInner::Uncompressed(reader) => reader.size,
+            FileReaderInner::Compressed(reader) => {
+                u64::try_from(reader.file_data.get_ref().len()).unwrap()
+            }
+            FileReaderInner::Slice(reader) => u64::try_from(reader.data().len()).unwrap(),
+        }
+    }
+
+    /// Returns true if the file is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(feature = "crc32")]
+impl<R> FileReader<'_, R>
+where
+    R: Read + Seek,
+{
+    /// Read the rest of the file, returning the data along with its CRC32.
+    pub fn read_to_end_with_crc32(&mut self) -> std::io::Result<(Vec<u8>, u32)> {
+        let mut data = Vec::new();
+        self.read_to_end(&mut data)?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&data);
+
+        Ok((data, hasher.finalize()))
+    }
+}
+
 impl<R> Read for FileReader<'_, R>
 where
-    R: Read,
+    R: Read + Seek,
 {
     fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
         match &mut self.inner {
             FileReaderInner::Uncompressed(reader) => reader.read(buffer),
             FileReaderInner::Compressed(reader) => reader.read(buffer),
+            FileReaderInner::Slice(reader) => reader.read(buffer),
+        }
+    }
+}
+
+impl<R> Seek for FileReader<'_, R>
+where
+    R: Read + Seek,
+{
+    /// Seek within the file.
+    ///
+    /// Seeking past the end of the file clamps to the end of the file.
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        match &mut self.inner {
+            FileReaderInner::Uncompressed(reader) => reader.seek(position),
+            FileReaderInner::Compressed(reader) => reader.seek(position),
+            FileReaderInner::Slice(reader) => reader.seek(position),
         }
     }
 }
 
+/// Calculate the new offset of a seek within a file, clamping it to the file size.
+fn get_seek_offset(position: SeekFrom, offset: u64, size: u64) -> std::io::Result<u64> {
+    let (base, delta) = match position {
+        SeekFrom::Start(offset) => return Ok(std::cmp::min(offset, size)),
+        SeekFrom::End(delta) => (size, delta),
+        SeekFrom::Current(delta) => (offset, delta),
+    };
+
+    let new_offset = base.checked_add_signed(delta).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })?;
+
+    Ok(std::cmp::min(new_offset, size))
+}
+
 #[derive(Debug)]
 pub(super) enum FileReaderInner<'a, R> {
     Uncompressed(UncompressedFileReaderInner<'a, R>),
     Compressed(CompressedFileReaderInner),
+    Slice(SliceFileReaderInner<'a, R>),
+}
+
+/// A handle to the underlying reader of an archive.
+#[derive(Debug)]
+pub(super) enum ReaderHandle<'a, R> {
+    /// A borrow of the archive's reader.
+    ///
+    /// We have exclusive access to the reader, so its position is always where we left it.
+    Borrowed(RwLockWriteGuard<'a, R>),
+
+    /// A clone of the archive's reader.
+    ///
+    /// Clones should have their own position, but we still seek before every read,
+    /// so clones that share one still work if they are only used from one thread.
+    Owned(R),
+}
+
+impl<R> Deref for ReaderHandle<'_, R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Borrowed(reader) => reader,
+            Self::Owned(reader) => reader,
+        }
+    }
+}
+
+impl<R> DerefMut for ReaderHandle<'_, R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Borrowed(reader) => reader,
+            Self::Owned(reader) => reader,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(super) struct UncompressedFileReaderInner<'a, R> {
-    pub(super) reader: std::cell::RefMut<'a, R>,
+    pub(super) reader: ReaderHandle<'a, R>,
     pub(super) key: Key,
+    pub(super) start: u64,
     pub(super) offset: u64,
     pub(super) size: u64,
 }
 
 impl<R> Read for UncompressedFileReaderInner<'_, R>
 where
-    R: Read,
+    R: Read + Seek,
 {
     fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
         if self.offset == self.size {
             return Ok(0);
         }
 
-        let limit = usize::try_from(self.size - self.offset).unwrap();
-        let limit = std::cmp::min(limit, buffer.len());
+        if let ReaderHandle::Owned(reader) = &mut self.reader {
+            reader.seek(SeekFrom::Start(self.start + self.offset))?;
+        }
+
+        // If the remaining size does not fit in a usize, it is larger than the buffer.
+        let limit = usize::try_from(self.size - self.offset).map_or(buffer.len(), |remaining| {
+            std::cmp::min(remaining, buffer.len())
+        });
 
         let n = self.reader.read(&mut buffer[..limit])?;
 
@@ -59,6 +179,23 @@ where
     }
 }
 
+impl<R> Seek for UncompressedFileReaderInner<'_, R>
+where
+    R: Seek,
+{
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let offset = get_seek_offset(position, self.offset, self.size)?;
+
+        // Owned readers seek before every read.
+        if let ReaderHandle::Borrowed(reader) = &mut self.reader {
+            reader.seek(SeekFrom::Start(self.start + offset))?;
+        }
+        self.offset = offset;
+
+        Ok(offset)
+    }
+}
+
 #[derive(Debug)]
 pub(super) struct CompressedFileReaderInner {
     pub(super) file_data: std::io::Cursor<Vec<u8>>,
@@ -70,8 +207,97 @@ impl Read for CompressedFileReaderInner {
     }
 }
 
+impl Seek for CompressedFileReaderInner {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let size = u64::try_from(self.file_data.get_ref().len()).unwrap();
+        let offset = get_seek_offset(position, self.file_data.position(), size)?;
+        self.file_data.set_position(offset);
+
+        Ok(offset)
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct SliceFileReaderInner<'a, R> {
+    /// A shared lock on the archive's reader, keeping the data alive.
+    pub(super) reader: RwLockReadGuard<'a, R>,
+
+    /// A function to get the archive data from the reader.
+    pub(super) get_data: fn(&R) -> &[u8],
+
+    /// The range of the file in the archive data.
+    pub(super) start: usize,
+    pub(super) end: usize,
+
+    pub(super) key: Key,
+    pub(super) offset: usize,
+}
+
+impl<R> SliceFileReaderInner<'_, R> {
+    /// Get the file data.
+    fn data(&self) -> &[u8] {
+        &(self.get_data)(&self.reader)[self.start..self.end]
+    }
+}
+
+impl<R> Read for SliceFileReaderInner<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.data();
+        let remaining = &data[self.offset..];
+        let n = std::cmp::min(remaining.len(), buffer.len());
+
+        let buffer = &mut buffer[..n];
+        buffer.copy_from_slice(&remaining[..n]);
+
+        // See UncompressedFileReaderInner for why the position is offset + size.
+        let size = data.len();
+        key_xor(u64::try_from(self.offset + size).unwrap(), self.key, buffer);
+
+        self.offset += n;
+
+        Ok(n)
+    }
+}
+
+impl<R> Seek for SliceFileReaderInner<'_, R> {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let size = u64::try_from(self.data().len()).unwrap();
+        let offset = get_seek_offset(position, u64::try_from(self.offset).unwrap(), size)?;
+        self.offset = usize::try_from(offset).unwrap();
+
+        Ok(offset)
+    }
+}
+
+/// Decompress file data.
+///
+/// The input is the compressed data of a file, after it has been decrypted.
+/// It starts with a 9 byte frame: the decompressed size (u32), the compressed size including the frame (u32), and the key code (u8).
+/// The size is the expected decompressed size, which must match the frame.
+///
+/// Returns [`Error::DecompressionFailed`] if the data is malformed.
+pub fn decompress_file_data(input: &[u8], size: u64) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    decompress_file_data_inner(input, Some(size), &mut output).ok_or(Error::DecompressionFailed)?;
+
+    Ok(output)
+}
+
+/// Decompress file data into a buffer, replacing its contents.
+///
+/// This is like [`decompress_file_data`], but reuses the allocation of the buffer.
+/// The expected decompressed size is taken from the frame.
+/// The contents of the buffer are unspecified if this returns an error.
+pub fn decompress_file_data_into(input: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+    decompress_file_data_inner(input, None, output).ok_or(Error::DecompressionFailed)
+}
+
 #[allow(clippy::get_first)]
-pub(super) fn decompress_file_data(mut input: &[u8], size: u64) -> Option<Vec<u8>> {
+fn decompress_file_data_inner(
+    mut input: &[u8],
+    expected_size: Option<u64>,
+    output: &mut Vec<u8>,
+) -> Option<()> {
     const MIN_COMPRESS: u16 = 4;
 
     if input.len() < 9 {
@@ -81,15 +307,19 @@ pub(super) fn decompress_file_data(mut input: &[u8], size: u64) -> Option<Vec<u8
     let src_size = u32::from_le_bytes(input[4..8].try_into().unwrap());
     let key_code = input[8];
 
-    if u64::from(dest_size) != size {
+    if expected_size.is_some_and(|size| u64::from(dest_size) != size) {
         return None;
     }
-    if u32::try_from(input.len()).unwrap() != src_size {
+    if u32::try_from(input.len()).ok()? != src_size {
         return None;
     }
     input = &input[9..];
 
-    let mut output = Vec::with_capacity(usize::try_from(size).unwrap());
+    let size = usize::try_from(dest_size).ok()?;
+    output.clear();
+    // The size in the frame isn't trusted, so only reserve a few times the input size up front.
+    // The output still grows past this if the data really is that compressible.
+    output.reserve(std::cmp::min(size, input.len().saturating_mul(4)));
     while !input.is_empty() {
         let input_0 = *input.get(0)?;
         if input_0 != key_code {
@@ -149,40 +379,275 @@ pub(super) fn decompress_file_data(mut input: &[u8], size: u64) -> Option<Vec<u8
         };
         index += 1;
 
+        // Back-references always copy bytes that already exist in the output,
+        // so we use extend_from_within to avoid zero-filling memory that would immediately be overwritten.
         let mut run_len = u32::from(run_len);
         if index < run_len {
             let mut num = index;
             while run_len > num {
-                let old_output_len = output.len();
                 let num_usize = usize::try_from(num).ok()?;
-
-                output.resize(old_output_len + num_usize, 0);
-                let start = old_output_len - num_usize;
-                output.copy_within(start..(start + num_usize), old_output_len);
+                let start = output.len().checked_sub(num_usize)?;
+                output.extend_from_within(start..(start + num_usize));
 
                 run_len -= num;
                 num += num;
             }
 
             if run_len != 0 {
-                let old_output_len = output.len();
                 let run_len_usize = usize::try_from(run_len).ok()?;
                 let num_usize = usize::try_from(num).ok()?;
 
-                output.resize(old_output_len + run_len_usize, 0);
-                let start = old_output_len - num_usize;
-                output.copy_within(start..(start + run_len_usize), old_output_len);
+                let start = output.len().checked_sub(num_usize)?;
+                output.extend_from_within(start..(start + run_len_usize));
             }
         } else {
-            let old_output_len = output.len();
             let run_len_usize = usize::try_from(run_len).ok()?;
             let index_usize = usize::try_from(index).ok()?;
 
-            output.resize(old_output_len + run_len_usize, 0);
-            let start = old_output_len - index_usize;
-            output.copy_within(start..(start + run_len_usize), old_output_len);
+            let start = output.len().checked_sub(index_usize)?;
+            output.extend_from_within(start..(start + run_len_usize));
+        }
+
+        // Don't let a malformed stream grow the output past its declared size.
+        if output.len() > size {
+            return None;
         }
     }
 
-    Some(output)
+    // A truncated stream ends early.
+    if output.len() != size {
+        return None;
+    }
+
+    Some(())
+}
+
+/// Compress file data into the format read by [`decompress_file_data`].
+///
+/// # Panics
+/// Panics if the input or compressed output is larger than `u32::MAX` bytes.
+pub(crate) fn compress_file_data(input: &[u8]) -> Vec<u8> {
+    const MIN_COMPRESS: usize = 4;
+    const MAX_RUN_LEN: usize = 0x1FFF + MIN_COMPRESS;
+    const MAX_DISTANCE: usize = 0x100_0000;
+    const MAX_CHAIN_LEN: usize = 64;
+    const HASH_BITS: u32 = 16;
+    const NONE: usize = usize::MAX;
+
+    fn hash(bytes: &[u8]) -> usize {
+        let value = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        usize::try_from(value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)).unwrap()
+    }
+
+    // The least common byte makes the best key code,
+    // as literal key codes must be escaped.
+    let mut counts = [0_usize; 256];
+    for byte in input {
+        counts[usize::from(*byte)] += 1;
+    }
+    let key_code = (0..=u8::MAX)
+        .min_by_key(|byte| counts[usize::from(*byte)])
+        .unwrap();
+
+    let dest_size = u32::try_from(input.len()).expect("input too large");
+    let mut output = Vec::with_capacity(9 + input.len());
+    output.extend(dest_size.to_le_bytes());
+    // The source size is filled in at the end.
+    output.extend([0; 4]);
+    output.push(key_code);
+
+    // Hash chains of previous positions, keyed by the hash of the next MIN_COMPRESS bytes.
+    let mut head = vec![NONE; 1 << HASH_BITS];
+    let mut prev = vec![NONE; input.len()];
+    let insert = |head: &mut [usize], prev: &mut [usize], position: usize| {
+        if position + MIN_COMPRESS <= input.len() {
+            let hash = hash(&input[position..]);
+            prev[position] = head[hash];
+            head[hash] = position;
+        }
+    };
+
+    let mut position = 0;
+    while position < input.len() {
+        let max_len = std::cmp::min(MAX_RUN_LEN, input.len() - position);
+        let mut best_len = 0;
+        let mut best_distance = 0;
+        if max_len >= MIN_COMPRESS {
+            let mut candidate = head[hash(&input[position..])];
+            let mut chain_len = 0;
+            while candidate != NONE && chain_len < MAX_CHAIN_LEN {
+                let distance = position - candidate;
+                if distance > MAX_DISTANCE {
+                    break;
+                }
+
+                let len = input[candidate..]
+                    .iter()
+                    .zip(&input[position..(position + max_len)])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = distance;
+
+                    if len == max_len {
+                        break;
+                    }
+                }
+
+                candidate = prev[candidate];
+                chain_len += 1;
+            }
+        }
+
+        let index = best_distance.wrapping_sub(1);
+        let index_size = match index {
+            0..=0xFF => 0,
+            0x100..=0xFFFF => 1,
+            _ => 2,
+        };
+        let run_len = best_len.wrapping_sub(MIN_COMPRESS);
+        let token_len = 2 + usize::from(run_len > 0x1F) + index_size + 1;
+
+        // Only emit a back-reference if it is no larger than the literals it replaces.
+        if best_len >= MIN_COMPRESS && token_len <= best_len {
+            let mut code = ((run_len & 0x1F) << 3) | index_size;
+            if run_len > 0x1F {
+                code |= 0x1 << 2;
+            }
+            // The key code cannot be used as a code, so codes at or above it are shifted up by one.
+            if code >= usize::from(key_code) {
+                code += 1;
+            }
+
+            output.push(key_code);
+            output.push(u8::try_from(code).unwrap());
+            if run_len > 0x1F {
+                output.push(u8::try_from(run_len >> 5).unwrap());
+            }
+            let index_bytes = u32::try_from(index).unwrap().to_le_bytes();
+            output.extend(&index_bytes[..(index_size + 1)]);
+
+            for position in position..(position + best_len) {
+                insert(&mut head, &mut prev, position);
+            }
+            position += best_len;
+        } else {
+            let byte = input[position];
+            output.push(byte);
+            if byte == key_code {
+                output.push(key_code);
+            }
+
+            insert(&mut head, &mut prev, position);
+            position += 1;
+        }
+    }
+
+    let src_size = u32::try_from(output.len()).expect("output too large");
+    output[4..8].copy_from_slice(&src_size.to_le_bytes());
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A small xorshift PRNG, so tests are deterministic.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn next_below(&mut self, n: usize) -> usize {
+            usize::try_from(self.next() % u64::try_from(n).unwrap()).unwrap()
+        }
+    }
+
+    fn assert_round_trip(input: &[u8]) {
+        let compressed = compress_file_data(input);
+        let size = u64::try_from(input.len()).unwrap();
+        let output = decompress_file_data(&compressed, size).expect("failed to decompress");
+        assert!(
+            output == input,
+            "round trip mismatch for input of len {}",
+            input.len()
+        );
+
+        let mut output = vec![0xFF; 16];
+        decompress_file_data_into(&compressed, &mut output).expect("failed to decompress");
+        assert!(output == input);
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+        assert_round_trip(&[]);
+        assert_round_trip(&[0; 100_000]);
+        assert_round_trip(&(0..=u8::MAX).cycle().take(10_000).collect::<Vec<_>>());
+
+        for _ in 0..200 {
+            let len = rng.next_below(4096);
+            let alphabet_len = 1 + rng.next_below(256);
+
+            // Random data
+            let input: Vec<u8> = (0..len)
+                .map(|_| u8::try_from(rng.next_below(alphabet_len)).unwrap())
+                .collect();
+            assert_round_trip(&input);
+
+            // Repetitive data
+            let mut input = Vec::with_capacity(len);
+            while input.len() < len {
+                if input.is_empty() || rng.next_below(4) == 0 {
+                    input.push(u8::try_from(rng.next_below(alphabet_len)).unwrap());
+                } else {
+                    let start = rng.next_below(input.len());
+                    let run_len = rng.next_below(64);
+                    for i in 0..run_len {
+                        input.push(input[start + i]);
+                    }
+                }
+            }
+            assert_round_trip(&input);
+        }
+
+        // Exercise far back-references, which need 3 byte indexes.
+        let block: Vec<u8> = (0..100_000)
+            .map(|_| u8::try_from(rng.next_below(256)).unwrap())
+            .collect();
+        let input = [block.as_slice(), block.as_slice()].concat();
+        let compressed = compress_file_data(&input);
+        assert!(compressed.len() < input.len());
+        assert_round_trip(&input);
+    }
+
+    #[test]
+    fn decompress_truncated_frame() {
+        // The data ends with a few literal bytes, so dropping one still leaves a valid stream.
+        let mut input: Vec<u8> = (0..=u8::MAX).cycle().take(1000).collect();
+        input.extend(b"end");
+        let mut compressed = compress_file_data(&input);
+
+        // Drop the last literal, fixing up the source size so only the output size is wrong.
+        compressed.truncate(compressed.len() - 1);
+        let src_size = u32::try_from(compressed.len()).unwrap();
+        compressed[4..8].copy_from_slice(&src_size.to_le_bytes());
+
+        assert!(matches!(
+            decompress_file_data(&compressed, 1003),
+            Err(Error::DecompressionFailed)
+        ));
+        assert!(matches!(
+            decompress_file_data_into(&compressed, &mut Vec::new()),
+            Err(Error::DecompressionFailed)
+        ));
+    }
 }
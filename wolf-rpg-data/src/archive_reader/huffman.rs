@@ -0,0 +1,283 @@
+/// The number of nodes in a Huffman tree, which has a leaf for every byte.
+const NUM_NODES: usize = 256 * 2 - 1;
+
+/// A node in a Huffman tree.
+#[derive(Debug)]
+struct Node {
+    weight: u64,
+    parent: Option<usize>,
+    children: [usize; 2],
+}
+
+/// Build a Huffman tree from the weight of each byte.
+///
+/// The first 256 nodes are the leaves for each byte, and the last node is the root.
+/// This must build the same tree as DxLib, including how ties are broken.
+fn build_tree(weights: &[u64; 256]) -> Vec<Node> {
+    let mut nodes: Vec<Node> = Vec::with_capacity(NUM_NODES);
+    nodes.extend(weights.iter().map(|weight| Node {
+        weight: *weight,
+        parent: None,
+        children: [0; 2],
+    }));
+
+    // Join the two lightest nodes without a parent until only the root is left.
+    // Ties go to the node that comes first.
+    while nodes.len() < NUM_NODES {
+        let mut min_1: Option<usize> = None;
+        let mut min_2: Option<usize> = None;
+        for (index, node) in nodes.iter().enumerate() {
+            if node.parent.is_some() {
+                continue;
+            }
+
+            if min_1.is_none_or(|min_1| nodes[min_1].weight > node.weight) {
+                min_2 = min_1;
+                min_1 = Some(index);
+            } else if min_2.is_none_or(|min_2| nodes[min_2].weight > node.weight) {
+                min_2 = Some(index);
+            }
+        }
+
+        // There are always at least two nodes without a parent until the root is made.
+        let min_1 = min_1.unwrap();
+        let min_2 = min_2.unwrap();
+
+        let index = nodes.len();
+        nodes[min_1].parent = Some(index);
+        nodes[min_2].parent = Some(index);
+        nodes.push(Node {
+            weight: nodes[min_1].weight.saturating_add(nodes[min_2].weight),
+            parent: None,
+            children: [min_1, min_2],
+        });
+    }
+
+    nodes
+}
+
+/// A reader for a stream of bits, starting with the most significant bit of each byte.
+#[derive(Debug)]
+struct BitReader<'a> {
+    input: &'a [u8],
+
+    /// The position in the input, in bits.
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    /// Read a single bit.
+    fn read_bit(&mut self) -> Option<u8> {
+        let byte = *self.input.get(self.position / 8)?;
+        let bit = (byte >> (7 - (self.position % 8))) & 1;
+        self.position += 1;
+
+        Some(bit)
+    }
+
+    /// Read a value of up to 64 bits.
+    fn read(&mut self, num_bits: u64) -> Option<u64> {
+        let mut value = 0;
+        for _ in 0..num_bits {
+            value = (value << 1) | u64::from(self.read_bit()?);
+        }
+
+        Some(value)
+    }
+
+    /// Read a size, which is stored as its number of bits followed by its value.
+    fn read_size(&mut self) -> Option<u64> {
+        let num_bits = self.read(6)? + 1;
+        self.read(num_bits)
+    }
+
+    /// Get the number of bytes that were read, counting a partially read byte.
+    fn num_bytes_read(&self) -> usize {
+        self.position.div_ceil(8)
+    }
+}
+
+/// Decode Huffman-coded data, as written by DxLib.
+///
+/// The data starts with the decoded size, the coded size, and the weight of each byte.
+/// The coded bits start at the next whole byte.
+/// If the decoded size is known, it must match the header.
+///
+/// Returns `None` if the data is malformed.
+pub(super) fn huffman_decode(input: &[u8], expected_size: Option<u64>) -> Option<Vec<u8>> {
+    let mut reader = BitReader::new(input);
+
+    let size = reader.read_size()?;
+    let _coded_size = reader.read_size()?;
+    if expected_size.is_some_and(|expected_size| expected_size != size) {
+        return None;
+    }
+
+    // The weights are stored as the difference from the weight of the previous byte.
+    let mut weights = [0_u64; 256];
+    for index in 0..weights.len() {
+        let num_bits = (reader.read(3)? + 1) * 2;
+        let is_negative = reader.read_bit()? == 1;
+        let value = reader.read(num_bits)?;
+
+        weights[index] = match index.checked_sub(1) {
+            None => value,
+            Some(previous) if is_negative => weights[previous].checked_sub(value)?,
+            Some(previous) => weights[previous].checked_add(value)?,
+        };
+    }
+
+    let mut reader = BitReader::new(input.get(reader.num_bytes_read()..)?);
+
+    // Every byte takes at least one bit, so a larger size can't be valid.
+    // Checking this first prevents huge allocations.
+    let size = usize::try_from(size).ok()?;
+    if size > reader.input.len().saturating_mul(8) {
+        return None;
+    }
+
+    let tree = build_tree(&weights);
+    let root = tree.len() - 1;
+
+    let mut output = Vec::with_capacity(size);
+    let mut node = root;
+    while output.len() < size {
+        let bit = reader.read_bit()?;
+        node = tree[node].children[usize::from(bit)];
+
+        if let Ok(byte) = u8::try_from(node) {
+            output.push(byte);
+            node = root;
+        }
+    }
+
+    Some(output)
+}
+
+/// A writer for a stream of bits, starting with the most significant bit of each byte.
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct BitWriter {
+    output: Vec<u8>,
+
+    /// The number of bits written.
+    position: usize,
+}
+
+#[cfg(test)]
+impl BitWriter {
+    /// Write the low bits of a value.
+    fn write(&mut self, value: u64, num_bits: u32) {
+        for shift in (0..num_bits).rev() {
+            if self.position.is_multiple_of(8) {
+                self.output.push(0);
+            }
+
+            let bit = u8::try_from((value >> shift) & 1).unwrap();
+            *self.output.last_mut().unwrap() |= bit << (7 - (self.position % 8));
+            self.position += 1;
+        }
+    }
+
+    /// Write a size in the format read by [`BitReader::read_size`].
+    fn write_size(&mut self, value: u64) {
+        let num_bits = std::cmp::max(u64::BITS - value.leading_zeros(), 1);
+        self.write(u64::from(num_bits - 1), 6);
+        self.write(value, num_bits);
+    }
+}
+
+/// Huffman-code data into the format read by [`huffman_decode`].
+///
+/// This is only used to make test archives.
+#[cfg(test)]
+pub(super) fn huffman_encode(input: &[u8]) -> Vec<u8> {
+    let mut counts = [0_u64; 256];
+    for byte in input {
+        counts[usize::from(*byte)] += 1;
+    }
+
+    // Scale the weights so the differences between them fit in the 16 bits the header allows.
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let mut weights = [0_u64; 256];
+    for (weight, count) in weights.iter_mut().zip(counts) {
+        if count != 0 {
+            *weight = std::cmp::max(count * 0xFFFF / max_count, 1);
+        }
+    }
+
+    let tree = build_tree(&weights);
+    let codes: Vec<Vec<u8>> = (0..256)
+        .map(|mut node| {
+            let mut code = Vec::new();
+            while let Some(parent) = tree[node].parent {
+                code.push(u8::from(tree[parent].children[1] == node));
+                node = parent;
+            }
+            code.reverse();
+            code
+        })
+        .collect();
+
+    let mut data = BitWriter::default();
+    for byte in input {
+        for bit in codes[usize::from(*byte)].iter() {
+            data.write(u64::from(*bit), 1);
+        }
+    }
+
+    let mut header = BitWriter::default();
+    header.write_size(u64::try_from(input.len()).unwrap());
+    header.write_size(u64::try_from(data.output.len()).unwrap());
+    let mut previous = 0;
+    for weight in weights {
+        let is_negative = weight < previous;
+        let value = weight.abs_diff(previous);
+        let num_bits = std::cmp::max(u64::BITS - value.leading_zeros(), 1).div_ceil(2) * 2;
+
+        header.write(u64::from(num_bits / 2 - 1), 3);
+        header.write(u64::from(is_negative), 1);
+        header.write(value, num_bits);
+        previous = weight;
+    }
+
+    let mut output = header.output;
+    output.extend(data.output);
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn huffman_round_trip() {
+        let inputs: [&[u8]; 4] = [
+            b"",
+            b"a",
+            b"[Config]\r\nTitle=Test\r\n",
+            &(0..=u8::MAX).cycle().take(10_000).collect::<Vec<_>>(),
+        ];
+        for input in inputs {
+            let encoded = huffman_encode(input);
+            let size = u64::try_from(input.len()).unwrap();
+            let decoded = huffman_decode(&encoded, Some(size)).expect("failed to decode");
+            assert!(decoded == input);
+        }
+    }
+
+    #[test]
+    fn huffman_decode_malformed() {
+        let input = [0xAB; 1000];
+        let encoded = huffman_encode(&input);
+
+        assert!(huffman_decode(&encoded, Some(999)).is_none());
+        assert!(huffman_decode(&encoded[..encoded.len() - 1], None).is_none());
+        assert!(huffman_decode(&[], None).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+use super::ArchiveReader;
+use crate::sanitize_components;
+use crate::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+impl<R> ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    /// Extract every file and dir in this archive into the given dir,
+    /// reading on this thread while other threads write to disk.
+    ///
+    /// Files are read and decompressed one at a time, as the underlying reader can't be shared,
+    /// but writing them overlaps with reading the next ones.
+    /// `num_writers` threads write files, and at most `capacity` files are held in memory while waiting to be written.
+    /// Both are raised to at least 1.
+    ///
+    /// Unlike the other `extract_all` methods, files may finish being written in any order.
+    /// See [`ArchiveReader::extract_all`].
+    pub fn extract_all_pipelined(
+        &self,
+        dest: &Path,
+        num_writers: usize,
+        capacity: usize,
+    ) -> Result<(), Error> {
+        let (root_dir, _total) = match self.prepare_extract_all(dest)? {
+            Some(prepared) => prepared,
+            None => return Ok(()),
+        };
+
+        let (sender, receiver) = sync_channel::<(PathBuf, Vec<u8>)>(capacity.max(1));
+        // The receiver is dropped when the last writer stops,
+        // so the reader can't block on a full channel that nothing is reading from.
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        std::thread::scope(|scope| {
+            let writers: Vec<_> = (0..num_writers.max(1))
+                .map(|_| {
+                    let receiver = receiver.clone();
+                    scope.spawn(move || -> Result<(), Error> {
+                        loop {
+                            let message = receiver
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .recv();
+                            let Ok((path, data)) = message else {
+                                return Ok(());
+                            };
+
+                            std::fs::write(path, data)?;
+                        }
+                    })
+                })
+                .collect();
+            drop(receiver);
+
+            let read_result = (|| {
+                for entry in self.walk_dir(root_dir)? {
+                    let entry = entry?;
+                    let file = entry.file();
+
+                    let path = dest.join(sanitize_components(entry.path_components())?);
+
+                    // Dirs are created here, before any of their files are sent.
+                    if file.is_dir() {
+                        std::fs::create_dir_all(path)?;
+                        continue;
+                    }
+
+                    // Opening the file checks the maximum decompressed size.
+                    let mut file_reader = self.get_file_reader(file)?;
+                    let mut data = Vec::new();
+                    file_reader.read_to_end(&mut data)?;
+
+                    // This only fails if every writer stopped because of an error, which is returned below.
+                    if sender.send((path, data)).is_err() {
+                        break;
+                    }
+                }
+
+                Ok(())
+            })();
+            drop(sender);
+
+            let mut result = read_result;
+            for writer in writers {
+                let writer_result = writer
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                if result.is_ok() {
+                    result = writer_result;
+                }
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ArchiveWriter;
+    use std::io::Cursor;
+
+    #[test]
+    fn extract_all_pipelined() {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(true);
+        writer.add_file("Config.ini", b"[Config]").unwrap();
+        writer
+            .add_file("Data/BasicData/CDataBase.dat", &[0xAB; 1000])
+            .unwrap();
+        writer.add_dir("Data/Empty").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+
+        let dest = std::env::temp_dir().join("wolf-rpg-data-extract-all-pipelined");
+        let result = reader.extract_all_pipelined(&dest, 2, 1);
+        let config = std::fs::read(dest.join("Config.ini"));
+        let database = std::fs::read(dest.join("Data/BasicData/CDataBase.dat"));
+        let has_empty_dir = dest.join("Data/Empty").is_dir();
+        std::fs::remove_dir_all(&dest).unwrap();
+
+        result.unwrap();
+        assert!(config.unwrap() == b"[Config]");
+        assert!(database.unwrap() == [0xAB; 1000]);
+        assert!(has_empty_dir);
+    }
+}
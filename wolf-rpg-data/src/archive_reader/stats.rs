@@ -0,0 +1,65 @@
+use super::FileEntry;
+use crate::Error;
+
+/// Statistics about an archive.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ArchiveStats {
+    /// The number of files.
+    pub file_count: u64,
+
+    /// The number of dirs, not counting the root dir.
+    pub dir_count: u64,
+
+    /// The total size of all files, after decompression.
+    pub uncompressed_size: u64,
+
+    /// The total size of all files, as stored in the archive.
+    ///
+    /// Files that are not compressed count towards this with their normal size.
+    /// Files in version 8 archives count with their Huffman-coded size, if they are Huffman-coded.
+    pub compressed_size: u64,
+}
+
+impl ArchiveStats {
+    /// Add a file or dir to these stats.
+    ///
+    /// `stored_size` is the size of the file's data in the archive, and is ignored for dirs.
+    /// The sizes come from the archive, so they are checked for overflow.
+    pub(super) fn add(&mut self, file_entry: &FileEntry, stored_size: u64) -> Result<(), Error> {
+        if file_entry.is_dir() {
+            self.dir_count = self
+                .dir_count
+                .checked_add(1)
+                .ok_or(Error::IntegerOverflow)?;
+            return Ok(());
+        }
+
+        self.file_count = self
+            .file_count
+            .checked_add(1)
+            .ok_or(Error::IntegerOverflow)?;
+        self.uncompressed_size = self
+            .uncompressed_size
+            .checked_add(file_entry.size())
+            .ok_or(Error::IntegerOverflow)?;
+        self.compressed_size = self
+            .compressed_size
+            .checked_add(stored_size)
+            .ok_or(Error::IntegerOverflow)?;
+
+        Ok(())
+    }
+
+    /// Get the ratio of the compressed size to the uncompressed size.
+    ///
+    /// Lower is better.
+    /// This is 1.0 if the archive has no file data.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.uncompressed_size == 0 {
+            return 1.0;
+        }
+
+        self.compressed_size as f64 / self.uncompressed_size as f64
+    }
+}
@@ -0,0 +1,57 @@
+use super::ArchiveReader;
+use crate::sanitize_components;
+use crate::Error;
+use std::io::Read;
+use std::io::Seek;
+use std::io::Write;
+use std::time::UNIX_EPOCH;
+
+impl<R> ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    /// Write every file and dir in this archive to a tar archive.
+    ///
+    /// Files are streamed into the tar archive, so nothing is written to disk.
+    /// Dirs are written as dir entries, so empty dirs are preserved.
+    /// Modified times are preserved where they are valid.
+    pub fn to_tar<W>(&self, writer: W) -> Result<W, Error>
+    where
+        W: Write,
+    {
+        let mut builder = tar::Builder::new(writer);
+
+        for entry in self.walk()? {
+            let entry = entry?;
+            let path_components = entry.path_components();
+            if path_components.is_empty() {
+                continue;
+            }
+            let path = sanitize_components(path_components)?;
+            let file = entry.file();
+
+            let mut header = tar::Header::new_gnu();
+            let mtime = file
+                .file_times()
+                .modified()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_secs());
+            header.set_mtime(mtime);
+
+            if file.is_dir() {
+                header.set_entry_type(tar::EntryType::Directory);
+                header.set_mode(0o755);
+                header.set_size(0);
+                builder.append_data(&mut header, path, std::io::empty())?;
+            } else {
+                header.set_entry_type(tar::EntryType::Regular);
+                header.set_mode(0o644);
+                header.set_size(file.size());
+                let reader = self.get_file_reader(file)?;
+                builder.append_data(&mut header, path, reader)?;
+            }
+        }
+
+        Ok(builder.into_inner()?)
+    }
+}
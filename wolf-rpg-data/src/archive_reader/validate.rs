@@ -0,0 +1,204 @@
+use super::file_entry_size;
+use super::ArchiveReader;
+use crate::Error;
+use std::io::Read;
+use std::io::Seek;
+
+/// A problem found by [`ArchiveReader::validate`].
+///
+/// Positions are relative to the start of their table.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// An error that stopped part of the validation
+    #[error(transparent)]
+    Archive(#[from] Error),
+
+    /// A file entry has a name position that is not in the file name table
+    #[error("file entry at {file_position} has an invalid name position {name_position}")]
+    InvalidFileNamePosition {
+        file_position: u64,
+        name_position: u64,
+    },
+
+    /// A file entry for a dir points at a dir entry that does not exist or is for another file entry
+    #[error("file entry at {file_position} has an invalid dir position {data_position}")]
+    InvalidDirectoryPosition {
+        file_position: u64,
+        data_position: u64,
+    },
+
+    /// A dir entry points at a file entry for itself that does not exist
+    #[error("dir entry at {dir_position} has an invalid file position {file_position}")]
+    InvalidDirectoryFilePosition {
+        dir_position: u64,
+        file_position: u64,
+    },
+
+    /// A child of a dir is not in the file table
+    #[error("child {index} of the dir entry at {dir_position} is not in the file table")]
+    InvalidDirectoryFile { dir_position: u64, index: u64 },
+
+    /// A dir has a parent that does not exist, or the root dir has a parent
+    #[error("dir entry at {dir_position} has an invalid parent")]
+    InvalidParent { dir_position: u64 },
+
+    /// A dir is its own ancestor
+    #[error("dir entry at {dir_position} is its own ancestor")]
+    DirectoryCycle { dir_position: u64 },
+
+    /// A file could not be read
+    #[error("failed to read \"{path}\"")]
+    Read {
+        path: String,
+        #[source]
+        error: Error,
+    },
+
+    /// A file did not have the size it declares
+    #[error("\"{path}\" should be {expected} bytes, but {actual} bytes were read")]
+    SizeMismatch {
+        path: String,
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl<R> ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    /// Check the whole archive for consistency.
+    ///
+    /// This checks that every name and dir position resolves,
+    /// that the dirs form a tree,
+    /// and that every file can be read and has its declared size.
+    /// Unlike the other methods, this collects every problem instead of stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        let header_data = match self.header_data.as_ref() {
+            Some(header_data) => header_data,
+            None => return Err(vec![ValidationError::Archive(Error::HeaderNotRead)]),
+        };
+
+        for (file_position, file_entry) in header_data.file_table.iter() {
+            if !header_data
+                .file_name_table
+                .contains_key(&file_entry.name_position)
+            {
+                errors.push(ValidationError::InvalidFileNamePosition {
+                    file_position: *file_position,
+                    name_position: file_entry.name_position,
+                });
+            }
+
+            // A dir must point back at the file entry for it.
+            let is_dir_valid = header_data
+                .directory_table
+                .get(&file_entry.data_position)
+                .is_some_and(|dir| dir.directory_position == *file_position);
+            if file_entry.is_dir() && !is_dir_valid {
+                errors.push(ValidationError::InvalidDirectoryPosition {
+                    file_position: *file_position,
+                    data_position: file_entry.data_position,
+                });
+            }
+        }
+
+        for (dir_position, dir) in header_data.directory_table.iter() {
+            let dir_position = *dir_position;
+
+            if !header_data.file_table.contains_key(&dir.directory_position) {
+                errors.push(ValidationError::InvalidDirectoryFilePosition {
+                    dir_position,
+                    file_position: dir.directory_position,
+                });
+            }
+
+            for index in 0..dir.num_files {
+                let file_position = index
+                    .checked_mul(u64::try_from(file_entry_size(header_data.version)).unwrap())
+                    .and_then(|offset| dir.file_head_position.checked_add(offset));
+                let is_valid = file_position.is_some_and(|file_position| {
+                    header_data.file_table.contains_key(&file_position)
+                });
+                if !is_valid {
+                    errors.push(ValidationError::InvalidDirectoryFile {
+                        dir_position,
+                        index,
+                    });
+                }
+            }
+
+            // Only the root dir is at position 0, and it is the only dir without a parent.
+            // Following the parents of any other dir must reach the root dir.
+            let is_root = dir_position == 0;
+            if is_root != dir.parent_directory_position.is_none() {
+                errors.push(ValidationError::InvalidParent { dir_position });
+                continue;
+            }
+            let mut current = dir;
+            let mut steps = 0;
+            while let Some(parent_position) = current.parent_directory_position {
+                current = match header_data.directory_table.get(&parent_position) {
+                    Some(parent) => parent,
+                    None => {
+                        errors.push(ValidationError::InvalidParent { dir_position });
+                        break;
+                    }
+                };
+
+                steps += 1;
+                if steps > header_data.directory_table.len() {
+                    errors.push(ValidationError::DirectoryCycle { dir_position });
+                    break;
+                }
+            }
+        }
+
+        // Walking relies on the structure checked above, so only read files if it is sound.
+        if errors.is_empty() {
+            let walk = match self.walk() {
+                Ok(walk) => walk,
+                Err(error) => return Err(vec![error.into()]),
+            };
+            for entry in walk.files_only() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(error) => {
+                        errors.push(error.into());
+                        break;
+                    }
+                };
+                let file_entry = entry.file();
+                let path = entry.path_components().join("/");
+
+                let result = self
+                    .get_file_reader(file_entry)
+                    .and_then(|mut file_reader| {
+                        Ok(std::io::copy(&mut file_reader, &mut std::io::sink())?)
+                    });
+                match result {
+                    Ok(actual) if actual != file_entry.size() => {
+                        errors.push(ValidationError::SizeMismatch {
+                            path,
+                            expected: file_entry.size(),
+                            actual,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        errors.push(ValidationError::Read { path, error });
+                    }
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+}
@@ -1,43 +1,79 @@
 use super::ArchiveReader;
 use super::FileEntry;
 use crate::Error;
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 /// An iterator over a dir and its descendants.
 #[derive(Debug)]
 pub struct WalkDirIter<'a, R> {
     archive_reader: &'a ArchiveReader<R>,
     stack: Vec<(&'a FileEntry, Vec<&'a str>)>,
+    max_depth: Option<usize>,
+
+    /// The positions of the dirs that were already visited, used to detect cycles.
+    visited_dirs: HashSet<u64>,
 }
 
 impl<'a, R> WalkDirIter<'a, R> {
     /// Make a new walk dir iter.
-    pub(super) fn new(archive_reader: &'a ArchiveReader<R>, file_entry: &'a FileEntry) -> Self {
+    ///
+    /// If no file is given, the iter is empty.
+    /// If a max depth is given, the children of entries at that depth are not visited.
+    pub(super) fn new(
+        archive_reader: &'a ArchiveReader<R>,
+        file_entry: Option<&'a FileEntry>,
+        max_depth: Option<usize>,
+    ) -> Self {
         Self {
             archive_reader,
-            stack: vec![(file_entry, Vec::new())],
+            stack: file_entry
+                .map(|file_entry| (file_entry, Vec::new()))
+                .into_iter()
+                .collect(),
+            max_depth,
+            visited_dirs: HashSet::new(),
         }
     }
 }
 
+impl<'a, R> WalkDirIter<'a, R> {
+    /// Only yield entries for files, skipping dirs.
+    ///
+    /// Dirs are still descended into.
+    pub fn files_only(self) -> impl Iterator<Item = Result<WalkDirEntry<'a>, Error>> {
+        self.filter(|entry| entry.as_ref().map_or(true, |entry| !entry.file().is_dir()))
+    }
+
+    /// Only yield entries for dirs, skipping files.
+    pub fn dirs_only(self) -> impl Iterator<Item = Result<WalkDirEntry<'a>, Error>> {
+        self.filter(|entry| entry.as_ref().map_or(true, |entry| entry.file().is_dir()))
+    }
+}
+
 impl<'a, R> Iterator for WalkDirIter<'a, R> {
     type Item = Result<WalkDirEntry<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         let (file_entry, path_components) = self.stack.pop()?;
 
-        if file_entry.is_dir() {
+        let at_max_depth = self
+            .max_depth
+            .is_some_and(|max_depth| path_components.len() >= max_depth);
+        if file_entry.is_dir() && !at_max_depth {
             let dir_entry = match self.archive_reader.get_dir_from_file(file_entry) {
                 Ok(dir_entry) => dir_entry,
                 Err(error) => return Some(Err(error)),
             };
+            if !self.visited_dirs.insert(dir_entry.directory_position) {
+                return Some(Err(Error::DirectoryCycle));
+            }
 
-            for file_index in (0..dir_entry.num_files()).rev() {
-                let file_index = usize::try_from(file_index).unwrap();
-                let file_entry = match self.archive_reader.get_dir_file(dir_entry, file_index) {
-                    Ok(Some(file_entry)) => file_entry,
-                    Ok(None) => return Some(Err(Error::InvalidFilePosition)),
-                    Err(error) => return Some(Err(error)),
-                };
+            let children = match self.archive_reader.get_children(dir_entry) {
+                Ok(children) => children,
+                Err(error) => return Some(Err(error)),
+            };
+            for file_entry in children.rev() {
                 let file_name = match self.archive_reader.get_file_name(file_entry) {
                     Ok(file_name) => file_name,
                     Err(error) => return Some(Err(error)),
@@ -50,6 +86,8 @@ impl<'a, R> Iterator for WalkDirIter<'a, R> {
             }
         }
 
+        log::trace!("walking \"{}\"", path_components.join("/"));
+
         Some(Ok(WalkDirEntry {
             file_entry,
             path_components,
@@ -76,4 +114,19 @@ impl<'a> WalkDirEntry<'a> {
     pub fn path_components(&self) -> &[&'a str] {
         self.path_components.as_slice()
     }
+
+    /// Get the depth of this entry, relative to the dir being walked.
+    ///
+    /// The dir being walked has a depth of 0, its children have a depth of 1, and so on.
+    pub fn depth(&self) -> usize {
+        self.path_components.len()
+    }
+
+    /// Get the path, relative to the dir being walked.
+    ///
+    /// Note that this is not sanitized.
+    /// Use [`sanitize_components`](crate::sanitize_components) before joining untrusted paths onto an output dir.
+    pub fn path(&self) -> PathBuf {
+        self.path_components.iter().collect()
+    }
 }
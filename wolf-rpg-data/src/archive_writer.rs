@@ -0,0 +1,680 @@
+use crate::archive_reader::compress_file_data;
+use crate::create_key;
+use crate::encoding_from_code_page;
+use crate::key_xor;
+use crate::ArchiveReader;
+use crate::Attributes;
+use crate::Error;
+use crate::FileEntry;
+use crate::FileTimes;
+use crate::Key;
+use crate::KeyString;
+use crate::DEFAULT_KEY_STRING;
+use crate::DIRECTORY_ENTRY_SIZE;
+use crate::FILE_ENTRY_SIZE;
+use crate::HEADER_SIZE;
+use encoding_rs::SHIFT_JIS;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+
+/// A writer for an archive.
+///
+/// File data is written as it is added.
+/// The header and tables are written when the archive is finished.
+#[derive(Debug)]
+pub struct ArchiveWriter<W> {
+    writer: W,
+    key: Key,
+
+    code_page: u64,
+    encoding: &'static encoding_rs::Encoding,
+    compression: bool,
+
+    /// The position of the next file's data, relative to the start of the data.
+    data_position: u64,
+    root: DirNode,
+}
+
+impl<W> ArchiveWriter<W> {
+    /// Create a writer for a Data.wolf file.
+    ///
+    /// The writer should be positioned at the start of the archive.
+    pub fn new(writer: W) -> Self {
+        let key = create_key(DEFAULT_KEY_STRING);
+        Self {
+            writer,
+            key,
+
+            code_page: 932,
+            encoding: SHIFT_JIS,
+            compression: false,
+
+            data_position: 0,
+            root: DirNode::default(),
+        }
+    }
+
+    /// Set the code page used to encode file names.
+    ///
+    /// This defaults to 932, Shift-JIS.
+    /// Returns [`Error::UnknownCodePage`] if names can't be encoded with it.
+    pub fn with_code_page(mut self, code_page: u64) -> Result<Self, Error> {
+        self.encoding =
+            encoding_from_code_page(code_page).ok_or(Error::UnknownCodePage { code_page })?;
+        self.code_page = code_page;
+
+        Ok(self)
+    }
+
+    /// Set whether added files should be compressed.
+    ///
+    /// Files are only stored compressed if compression makes them smaller.
+    /// This is disabled by default.
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Use a custom key string to encrypt the archive.
+    pub fn with_key_string(mut self, key_string: KeyString) -> Self {
+        self.key = create_key(key_string);
+        self
+    }
+
+    /// Get the dir for the given path components, creating it and its parents if needed.
+    fn get_or_create_dir(
+        &mut self,
+        path: &str,
+        components: &[&str],
+    ) -> Result<&mut DirNode, Error> {
+        let mut dir = &mut self.root;
+        for component in components {
+            let node = dir
+                .children
+                .entry((*component).to_string())
+                .or_insert_with(|| Node::Dir(DirNode::default()));
+
+            dir = match node {
+                Node::Dir(dir) => dir,
+                Node::File(_) => {
+                    return Err(Error::InvalidPath {
+                        path: path.to_string(),
+                    });
+                }
+            };
+        }
+
+        Ok(dir)
+    }
+
+    /// Add a dir, creating its parents if needed.
+    ///
+    /// Adding a dir that already exists updates its file times.
+    pub fn add_dir_with_times(&mut self, path: &str, file_times: FileTimes) -> Result<(), Error> {
+        let components = split_path(path)?;
+        let dir = self.get_or_create_dir(path, &components)?;
+        dir.file_times = file_times;
+
+        Ok(())
+    }
+
+    /// Add a dir, creating its parents if needed.
+    pub fn add_dir(&mut self, path: &str) -> Result<(), Error> {
+        self.add_dir_with_times(path, FileTimes::default())
+    }
+}
+
+impl<W> ArchiveWriter<W>
+where
+    W: Write + Seek,
+{
+    /// Add a file, creating its parents if needed.
+    pub fn add_file_with_times(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        file_times: FileTimes,
+    ) -> Result<(), Error> {
+        let data_size = u64::try_from(data.len()).unwrap();
+
+        if self.compression && !data.is_empty() && u32::try_from(data.len()).is_ok() {
+            let compressed = compress_file_data(data);
+            if compressed.len() < data.len() {
+                return self.add_stored_file(
+                    path,
+                    compressed,
+                    data_size,
+                    true,
+                    Attributes::Archive,
+                    file_times,
+                );
+            }
+        }
+
+        self.add_stored_file(
+            path,
+            data.to_vec(),
+            data_size,
+            false,
+            Attributes::Archive,
+            file_times,
+        )
+    }
+
+    /// Add a file that is already compressed, creating its parents if needed.
+    ///
+    /// `size` is the size of the data after decompression.
+    /// The data is stored as-is, so it must be in the format read by [`decompress_file_data`](crate::decompress_file_data).
+    pub fn add_compressed_file_with_times(
+        &mut self,
+        path: &str,
+        compressed_data: &[u8],
+        size: u64,
+        file_times: FileTimes,
+    ) -> Result<(), Error> {
+        self.add_stored_file(
+            path,
+            compressed_data.to_vec(),
+            size,
+            true,
+            Attributes::Archive,
+            file_times,
+        )
+    }
+
+    /// Copy the dirs and files of an archive into this one.
+    ///
+    /// Only entries whose paths the filter returns `true` for are copied,
+    /// so files can be replaced by skipping them here and adding them afterwards.
+    /// File data is copied as it is stored, so compressed files are not recompressed.
+    pub fn copy_from<R, F>(&mut self, reader: &ArchiveReader<R>, mut filter: F) -> Result<(), Error>
+    where
+        R: Read + Seek,
+        F: FnMut(&str, &FileEntry) -> bool,
+    {
+        for entry in reader.walk()? {
+            let entry = entry?;
+            let path = entry.path_components().join("/");
+            let file = entry.file();
+            if path.is_empty() || !filter(&path, file) {
+                continue;
+            }
+
+            if file.is_dir() {
+                self.add_dir_with_times(&path, file.file_times())?;
+            } else {
+                let data = reader.read_raw_file_data(file)?;
+                self.add_stored_file(
+                    &path,
+                    data,
+                    file.size(),
+                    file.is_compressed(),
+                    file.get_attributes(),
+                    file.file_times(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a file with data as it should be stored, creating its parents if needed.
+    #[allow(clippy::too_many_arguments)]
+    fn add_stored_file(
+        &mut self,
+        path: &str,
+        mut data: Vec<u8>,
+        data_size: u64,
+        is_compressed: bool,
+        attributes: Attributes,
+        file_times: FileTimes,
+    ) -> Result<(), Error> {
+        let components = split_path(path)?;
+        let (file_name, parent_components) = components.split_last().unwrap();
+
+        let data_position = self.data_position;
+        let compressed_data_size = is_compressed.then(|| u64::try_from(data.len()).unwrap());
+
+        let parent = self.get_or_create_dir(path, parent_components)?;
+        if parent.children.contains_key(*file_name) {
+            return Err(Error::DuplicatePath {
+                path: path.to_string(),
+            });
+        }
+        parent.children.insert(
+            (*file_name).to_string(),
+            Node::File(FileNode {
+                attributes,
+                file_times,
+                data_position,
+                data_size,
+                compressed_data_size,
+            }),
+        );
+
+        key_xor(data_size, self.key, &mut data);
+
+        self.writer
+            .seek(SeekFrom::Start(HEADER_SIZE + data_position))?;
+        self.writer.write_all(&data)?;
+        self.data_position += u64::try_from(data.len()).unwrap();
+
+        Ok(())
+    }
+
+    /// Add a file, creating its parents if needed.
+    pub fn add_file(&mut self, path: &str, data: &[u8]) -> Result<(), Error> {
+        self.add_file_with_times(path, data, FileTimes::default())
+    }
+
+    /// Encode a file name for the file name table.
+    fn encode_file_name(&self, file_name: &str, buffer: &mut Vec<u8>) -> Result<(), Error> {
+        if file_name.is_empty() {
+            buffer.extend([0; 4]);
+            return Ok(());
+        }
+
+        let (bytes, _, had_errors) = self.encoding.encode(file_name);
+        if had_errors {
+            return Err(Error::InvalidFileName);
+        }
+        let file_name_upper = file_name.to_ascii_uppercase();
+        let (bytes_upper, _, had_errors) = self.encoding.encode(&file_name_upper);
+        if had_errors {
+            return Err(Error::InvalidFileName);
+        }
+
+        // The name is stored in 4 byte units and must have room for a nul terminator.
+        let len = (std::cmp::max(bytes.len(), bytes_upper.len()) + 1).div_ceil(4);
+        let len_u16 = u16::try_from(len).map_err(|_| Error::InvalidFileName)?;
+
+        let mut bytes = bytes.into_owned();
+        bytes.resize(len * 4, 0);
+        let mut bytes_upper = bytes_upper.into_owned();
+        bytes_upper.resize(len * 4, 0);
+
+        let parity = bytes_upper
+            .iter()
+            .fold(0_u16, |acc, byte| acc.wrapping_add((*byte).into()));
+
+        buffer.extend(len_u16.to_le_bytes());
+        buffer.extend(parity.to_le_bytes());
+        buffer.extend(bytes_upper);
+        buffer.extend(bytes);
+
+        Ok(())
+    }
+
+    /// Write the header and tables, finishing the archive.
+    ///
+    /// This returns the inner writer.
+    pub fn finish(mut self) -> Result<W, Error> {
+        let mut file_name_table = Vec::new();
+        let mut file_table = Vec::new();
+        let mut directory_table = Vec::new();
+
+        // The root dir's file entry is always first, followed by the children of each dir in breadth-first order.
+        // The children of a dir must be contiguous, as dirs only store the position of the first child.
+        self.encode_file_name("", &mut file_name_table)?;
+        file_table.push(RawFileEntry {
+            name_position: 0,
+            attributes: Attributes::Directory,
+            file_times: self.root.file_times,
+            data_position: 0,
+            data_size: 0,
+            compressed_data_size: None,
+        });
+
+        let mut queue = VecDeque::new();
+        queue.push_back((&self.root, 0, None));
+        while let Some((dir, file_index, parent_directory_position)) = queue.pop_front() {
+            let directory_position =
+                u64::try_from(directory_table.len() * DIRECTORY_ENTRY_SIZE).unwrap();
+            file_table[file_index].data_position = directory_position;
+
+            directory_table.push(RawDirectoryEntry {
+                directory_position: u64::try_from(file_index * FILE_ENTRY_SIZE).unwrap(),
+                parent_directory_position,
+                num_files: u64::try_from(dir.children.len()).unwrap(),
+                file_head_position: u64::try_from(file_table.len() * FILE_ENTRY_SIZE).unwrap(),
+            });
+
+            for (file_name, node) in dir.children.iter() {
+                let name_position = u64::try_from(file_name_table.len()).unwrap();
+                self.encode_file_name(file_name, &mut file_name_table)?;
+
+                match node {
+                    Node::Dir(child) => {
+                        queue.push_back((child, file_table.len(), Some(directory_position)));
+                        file_table.push(RawFileEntry {
+                            name_position,
+                            attributes: Attributes::Directory,
+                            file_times: child.file_times,
+                            // This is filled in when the dir is visited.
+                            data_position: 0,
+                            data_size: 0,
+                            compressed_data_size: None,
+                        });
+                    }
+                    Node::File(file) => {
+                        file_table.push(RawFileEntry {
+                            name_position,
+                            attributes: file.attributes,
+                            file_times: file.file_times,
+                            data_position: file.data_position,
+                            data_size: file.data_size,
+                            compressed_data_size: file.compressed_data_size,
+                        });
+                    }
+                }
+            }
+        }
+
+        let file_table_position = u64::try_from(file_name_table.len()).unwrap();
+        let directory_table_position =
+            file_table_position + u64::try_from(file_table.len() * FILE_ENTRY_SIZE).unwrap();
+        let file_header_size = directory_table_position
+            + u64::try_from(directory_table.len() * DIRECTORY_ENTRY_SIZE).unwrap();
+        let file_header_size =
+            u32::try_from(file_header_size).map_err(|_| Error::HeaderTooLarge)?;
+
+        let mut tables = file_name_table;
+        for file_entry in file_table.iter() {
+            file_entry.encode(&mut tables);
+        }
+        for directory_entry in directory_table.iter() {
+            directory_entry.encode(&mut tables);
+        }
+
+        let file_name_table_position = HEADER_SIZE + self.data_position;
+        key_xor(file_name_table_position, self.key, &mut tables);
+        self.writer
+            .seek(SeekFrom::Start(file_name_table_position))?;
+        self.writer.write_all(&tables)?;
+
+        let mut header = Vec::with_capacity(usize::try_from(HEADER_SIZE).unwrap());
+        header.extend(b"DX");
+        header.extend(6_u16.to_le_bytes());
+        header.extend(file_header_size.to_le_bytes());
+        header.extend(HEADER_SIZE.to_le_bytes());
+        header.extend(file_name_table_position.to_le_bytes());
+        header.extend(file_table_position.to_le_bytes());
+        header.extend(directory_table_position.to_le_bytes());
+        header.extend(self.code_page.to_le_bytes());
+        key_xor(0, self.key, &mut header);
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Split a path into its components.
+fn split_path(path: &str) -> Result<Vec<&str>, Error> {
+    let components: Vec<_> = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    if components.is_empty() {
+        return Err(Error::InvalidPath {
+            path: path.to_string(),
+        });
+    }
+
+    Ok(components)
+}
+
+#[derive(Debug)]
+enum Node {
+    Dir(DirNode),
+    File(FileNode),
+}
+
+#[derive(Debug, Default)]
+struct DirNode {
+    file_times: FileTimes,
+    children: BTreeMap<String, Node>,
+}
+
+#[derive(Debug)]
+struct FileNode {
+    attributes: Attributes,
+    file_times: FileTimes,
+    data_position: u64,
+    data_size: u64,
+    compressed_data_size: Option<u64>,
+}
+
+/// A file entry, as laid out in the file table.
+#[derive(Debug)]
+struct RawFileEntry {
+    name_position: u64,
+    attributes: Attributes,
+    file_times: FileTimes,
+    data_position: u64,
+    data_size: u64,
+    compressed_data_size: Option<u64>,
+}
+
+impl RawFileEntry {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend(self.name_position.to_le_bytes());
+        buffer.extend(self.attributes.bits().to_le_bytes());
+        buffer.extend(self.file_times.created.to_le_bytes());
+        buffer.extend(self.file_times.accessed.to_le_bytes());
+        buffer.extend(self.file_times.modified.to_le_bytes());
+        buffer.extend(self.data_position.to_le_bytes());
+        buffer.extend(self.data_size.to_le_bytes());
+        buffer.extend(self.compressed_data_size.unwrap_or(u64::MAX).to_le_bytes());
+    }
+}
+
+/// A directory entry, as laid out in the directory table.
+#[derive(Debug)]
+struct RawDirectoryEntry {
+    directory_position: u64,
+    parent_directory_position: Option<u64>,
+    num_files: u64,
+    file_head_position: u64,
+}
+
+impl RawDirectoryEntry {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend(self.directory_position.to_le_bytes());
+        buffer.extend(
+            self.parent_directory_position
+                .unwrap_or(u64::MAX)
+                .to_le_bytes(),
+        );
+        buffer.extend(self.num_files.to_le_bytes());
+        buffer.extend(self.file_head_position.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn round_trip() {
+        round_trip_with_compression(false);
+        round_trip_with_compression(true);
+    }
+
+    fn round_trip_with_compression(compression: bool) {
+        let mut file_times = FileTimes::default();
+        file_times
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            .unwrap();
+        file_times
+            .set_created(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            .unwrap();
+
+        let files: &[(&str, &[u8])] = &[
+            ("Data/BasicData/Game.dat", b"game data"),
+            ("Data/BasicData/CDataBase.dat", &[0xAB; 1000]),
+            ("Data/MapData/Map001.mps", b""),
+            ("Data/テスト.txt", "こんにちは".as_bytes()),
+            ("Config.ini", b"[Config]"),
+        ];
+
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(compression);
+        for (path, data) in files {
+            writer.add_file_with_times(path, data, file_times).unwrap();
+        }
+        writer.add_dir("Data/Empty").unwrap();
+        assert!(matches!(
+            writer.add_file("Config.ini", b""),
+            Err(Error::DuplicatePath { .. })
+        ));
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive));
+        reader.read_header().unwrap();
+
+        let root_dir = reader.get_root_dir().unwrap().unwrap();
+        let mut num_files = 0;
+        let mut found_empty_dir = false;
+        for entry in reader.walk_dir(root_dir).unwrap() {
+            let entry = entry.unwrap();
+            let path = entry.path_components().join("/");
+            let file = entry.file();
+
+            if file.is_dir() {
+                found_empty_dir |= path == "Data/Empty";
+                continue;
+            }
+
+            let (_, expected_data) = files
+                .iter()
+                .find(|(expected_path, _)| *expected_path == path)
+                .unwrap();
+            let mut data = Vec::new();
+            reader
+                .get_file_reader(file)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert!(data == *expected_data, "data mismatch for \"{path}\"");
+            if compression && path == "Data/BasicData/CDataBase.dat" {
+                assert!(file.is_compressed());
+            }
+
+            let times = file.file_times();
+            assert!(times.modified() == file_times.modified());
+            assert!(times.created() == file_times.created());
+
+            num_files += 1;
+        }
+
+        assert!(num_files == files.len());
+        assert!(found_empty_dir);
+        let empty_dir = reader.get_file_by_path("Data/Empty").unwrap().unwrap();
+        let empty_dir = reader.get_dir_from_file(empty_dir).unwrap();
+        assert!(reader.get_children(empty_dir).unwrap().next().is_none());
+
+        let stats = reader.stats().unwrap();
+        assert!(stats.file_count == 5);
+        assert!(stats.dir_count == 4);
+        assert!(stats.uncompressed_size == 1032);
+        assert!((stats.compressed_size < stats.uncompressed_size) == compression);
+    }
+
+    #[test]
+    fn custom_key_string() {
+        let key_string = KeyString::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_key_string(key_string);
+        writer.add_file("Config.ini", b"[Config]").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::new(Cursor::new(archive.as_slice()));
+        assert!(reader.read_header().is_err());
+
+        let mut reader =
+            ArchiveReader::with_key_string(Cursor::new(archive.as_slice()), key_string);
+        reader.read_header().unwrap();
+        assert!(reader.read("Config.ini").unwrap() == b"[Config]");
+    }
+
+    #[test]
+    fn copy_from() {
+        let mut file_times = FileTimes::default();
+        file_times
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+            .unwrap();
+
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_compression(true);
+        writer
+            .add_file_with_times("Data/BasicData/CDataBase.dat", &[0xAB; 1000], file_times)
+            .unwrap();
+        writer.add_file("Data/Old.txt", b"old").unwrap();
+        writer.add_file("Config.ini", b"[Config]").unwrap();
+        let mut archive = writer.finish().unwrap().into_inner();
+
+        // The writer always uses the archive attribute, so patch in others to check that they are copied.
+        let attributes = Attributes::ReadOnly | Attributes::Hidden | Attributes::Archive;
+        let mut old_reader = ArchiveReader::new(Cursor::new(archive.as_slice()));
+        old_reader.read_header().unwrap();
+        let file_name_table_position = old_reader.file_name_table_position().unwrap();
+        let file_table_position = old_reader.file_table_position().unwrap();
+        let header_size = old_reader.header_size().unwrap();
+        let position = old_reader
+            .get_file_by_path("Data/BasicData/CDataBase.dat")
+            .unwrap()
+            .unwrap()
+            .position();
+        let key = create_key(DEFAULT_KEY_STRING);
+        let start = usize::try_from(file_name_table_position).unwrap();
+        let end = start + usize::try_from(header_size).unwrap();
+        let tables = &mut archive[start..end];
+        key_xor(file_name_table_position, key, tables);
+        let attributes_position = usize::try_from(file_table_position + position).unwrap() + 8;
+        tables[attributes_position..attributes_position + 8]
+            .copy_from_slice(&attributes.bits().to_le_bytes());
+        key_xor(file_name_table_position, key, tables);
+
+        let mut old_reader = ArchiveReader::new(Cursor::new(archive));
+        old_reader.read_header().unwrap();
+
+        // Re-encrypting with a different key checks that the copied data is decrypted first.
+        let key_string = KeyString::new([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new())).with_key_string(key_string);
+        writer
+            .copy_from(&old_reader, |path, _file| {
+                path != "Data/Old.txt" && path != "Config.ini"
+            })
+            .unwrap();
+        writer.add_file("Config.ini", b"[Config]\nA=1").unwrap();
+        writer.add_file("Data/New.txt", b"new").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+        let mut reader = ArchiveReader::with_key_string(Cursor::new(archive), key_string);
+        reader.read_header().unwrap();
+
+        let old_file = old_reader
+            .get_file_by_path("Data/BasicData/CDataBase.dat")
+            .unwrap()
+            .unwrap();
+        let file = reader
+            .get_file_by_path("Data/BasicData/CDataBase.dat")
+            .unwrap()
+            .unwrap();
+        assert!(file.compressed_size() == old_file.compressed_size());
+        assert!(file.file_times().modified() == file_times.modified());
+        assert!(file.get_attributes() == attributes);
+        assert!(reader.read("Data/BasicData/CDataBase.dat").unwrap() == [0xAB; 1000]);
+        assert!(reader.read("Config.ini").unwrap() == b"[Config]\nA=1");
+        assert!(reader.read("Data/New.txt").unwrap() == b"new");
+        assert!(!reader.exists("Data/Old.txt").unwrap());
+    }
+}
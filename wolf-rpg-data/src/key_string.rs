@@ -1,2 +1,82 @@
+use crate::Error;
+use crate::KEY_LEN;
+
 /// A key string
-pub struct KeyString(pub [u8; 12]);
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyString(pub [u8; KEY_LEN]);
+
+impl KeyString {
+    /// Create a key string from raw bytes.
+    pub const fn new(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parse a key string from hex, like `38 50 40 28 72 4F 21 70 3B 73 35 38`.
+    ///
+    /// Whitespace is ignored.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits: Vec<u8> = hex
+            .bytes()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .map(|byte| match byte {
+                b'0'..=b'9' => Ok(byte - b'0'),
+                b'a'..=b'f' => Ok(byte - b'a' + 10),
+                b'A'..=b'F' => Ok(byte - b'A' + 10),
+                _ => Err(Error::InvalidKeyHex),
+            })
+            .collect::<Result<_, _>>()?;
+
+        if !digits.len().is_multiple_of(2) {
+            return Err(Error::InvalidKeyHex);
+        }
+
+        let len = digits.len() / 2;
+        if len != KEY_LEN {
+            return Err(Error::InvalidKeyLength { len });
+        }
+
+        let mut bytes = [0; KEY_LEN];
+        for (byte, digits) in bytes.iter_mut().zip(digits.chunks(2)) {
+            *byte = (digits[0] << 4) | digits[1];
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Get the raw bytes of this key string.
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DEFAULT_KEY_STRING;
+
+    #[test]
+    fn from_hex() {
+        let key_string = KeyString::from_hex("38 50 40 28 72 4F 21 70 3B 73 35 38").unwrap();
+        assert!(key_string == DEFAULT_KEY_STRING);
+
+        let key_string = KeyString::from_hex("38504028724f21703b733538").unwrap();
+        assert!(key_string == DEFAULT_KEY_STRING);
+
+        assert!(matches!(
+            KeyString::from_hex("38 50 40"),
+            Err(Error::InvalidKeyLength { len: 3 })
+        ));
+        assert!(matches!(
+            KeyString::from_hex("38 50 40 28 72 4F 21 70 3B 73 35 38 00"),
+            Err(Error::InvalidKeyLength { len: 13 })
+        ));
+        assert!(matches!(
+            KeyString::from_hex("38 50 40 28 72 4F 21 70 3B 73 35 3G"),
+            Err(Error::InvalidKeyHex)
+        ));
+        assert!(matches!(
+            KeyString::from_hex("38 50 40 28 72 4F 21 70 3B 73 35 3"),
+            Err(Error::InvalidKeyHex)
+        ));
+    }
+}
@@ -1,25 +1,71 @@
 mod archive_reader;
+mod archive_writer;
 mod key_string;
+mod multi_archive_reader;
+mod natural_sort;
+mod sanitize;
+#[cfg(any(unix, windows))]
+mod shared_file;
 
+pub use self::archive_reader::decompress_file_data;
+pub use self::archive_reader::decompress_file_data_into;
+pub use self::archive_reader::find_archive_offset;
 pub use self::archive_reader::ArchiveReader;
+pub use self::archive_reader::ArchiveReaderBuilder;
+pub use self::archive_reader::ArchiveStats;
+pub use self::archive_reader::Attributes;
 pub use self::archive_reader::DirectoryEntry;
 pub use self::archive_reader::FileEntry;
 pub use self::archive_reader::FileReader;
 pub use self::archive_reader::FileTimes;
+pub use self::archive_reader::ValidationError;
+pub use self::archive_reader::WalkDirEntry;
 pub use self::archive_reader::WalkDirIter;
+pub use self::archive_writer::ArchiveWriter;
 pub use self::key_string::KeyString;
+pub use self::multi_archive_reader::MultiArchiveEntry;
+pub use self::multi_archive_reader::MultiArchiveReader;
+pub use self::natural_sort::natural_cmp;
+pub use self::natural_sort::natural_path_cmp;
+pub use self::sanitize::find_path_collisions;
+pub use self::sanitize::sanitize_components;
+#[cfg(any(unix, windows))]
+pub use self::shared_file::SharedFile;
 
 const KEY_LEN: usize = 12;
 
+/// The size of the archive header, in bytes.
+const HEADER_SIZE: u64 = 48;
+
+/// The size of a file entry in the file table, in bytes.
+const FILE_ENTRY_SIZE: usize = 64;
+
+/// The size of a file entry in the file table of a version 8 archive, in bytes.
+const FILE_ENTRY_SIZE_V8: usize = 72;
+
+/// The size of a directory entry in the directory table, in bytes.
+const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+/// The size of a version 8 archive header, in bytes.
+const HEADER_SIZE_V8: u64 = 64;
+
+/// The length of a version 8 key, in bytes.
+const KEY_LEN_V8: usize = 7;
+
 /// A key
 type Key = [u8; KEY_LEN];
 
-const DEFAULT_KEY_STRING: KeyString = KeyString([
+/// The key string used by most archives.
+pub const DEFAULT_KEY_STRING: KeyString = KeyString::new([
     0x38, 0x50, 0x40, 0x28, 0x72, 0x4F, 0x21, 0x70, 0x3B, 0x73, 0x35, 0x38,
 ]);
 
 /// The error type
+///
+/// More variants may be added in the future.
+/// Use [`Error::kind`] to handle errors by category.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error {
     /// An io error
     #[error("io error")]
@@ -34,8 +80,8 @@ pub enum Error {
     InvalidVersion { version: u16 },
 
     /// Invalid file name parity
-    #[error("invalid file name parity")]
-    InvalidFileNameParity,
+    #[error("invalid file name parity at offset {offset:#X}")]
+    InvalidFileNameParity { offset: u64 },
 
     /// Invalid file name
     #[error("invalid file name")]
@@ -81,30 +127,221 @@ pub enum Error {
     #[error("decompression failed")]
     DecompressionFailed,
 
+    /// A compressed file is larger than the maximum decompressed size
+    #[error("compressed file size {size} exceeds the maximum of {max}")]
+    DecompressedSizeTooLarge { size: u64, max: u64 },
+
     /// A file position was invalid
     #[error("invalid file position")]
     InvalidFilePosition,
+
+    /// The upper-case file name does not match the file name
+    ///
+    /// This is only checked if [`ArchiveReader::strict_names`] is set.
+    #[error("file name case mismatch at offset {offset:#X}")]
+    FilenameCaseMismatch { offset: u64 },
+
+    /// A path was invalid
+    #[error("invalid path \"{path}\"")]
+    InvalidPath { path: String },
+
+    /// A path was added more than once
+    #[error("duplicate path \"{path}\"")]
+    DuplicatePath { path: String },
+
+    /// The header is too large
+    #[error("header too large")]
+    HeaderTooLarge,
+
+    /// A key had the wrong length
+    #[error("invalid key length {len}, expected {KEY_LEN}")]
+    InvalidKeyLength { len: usize },
+
+    /// A path component was unsafe to extract
+    #[error("invalid path component \"{}\"", component.escape_debug())]
+    InvalidPathComponent { component: String },
+
+    /// A key was not valid hex
+    #[error("invalid key hex")]
+    InvalidKeyHex,
+
+    /// The table positions or sizes in the header are inconsistent
+    #[error("invalid header")]
+    InvalidHeader,
+
+    /// A dir contains itself, directly or through its descendants
+    #[error("directory cycle")]
+    DirectoryCycle,
+
+    /// A size or position from the archive did not fit in an integer
+    #[error("integer overflow")]
+    IntegerOverflow,
+
+    /// A path does not exist in the archive
+    #[error("\"{path}\" not found")]
+    NotFound { path: String },
+
+    /// A text file was not valid in the archive's encoding
+    #[error("invalid text")]
+    InvalidText,
+
+    /// An io error while reading the archive, at a known offset in the archive
+    #[error("io error at offset {offset:#X}")]
+    IoAt {
+        offset: u64,
+        #[source]
+        error: std::io::Error,
+    },
+
+    /// Two paths would be extracted to the same path on a case-insensitive filesystem
+    #[error("\"{first}\" and \"{second}\" would be extracted to the same path")]
+    PathCollision { first: String, second: String },
+
+    /// An operation was cancelled by the caller
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// Get the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::Io(_) | Self::IoAt { .. } => ErrorKind::Io,
+            Self::InvalidMagic { .. }
+            | Self::InvalidVersion { .. }
+            | Self::InvalidFileNameParity { .. }
+            | Self::InvalidDirectoryFileIndex
+            | Self::InvalidFileNamePosition
+            | Self::InvalidDirectoryPosition
+            | Self::DecompressionFailed
+            | Self::InvalidFilePosition
+            | Self::FilenameCaseMismatch { .. }
+            | Self::InvalidHeader
+            | Self::DirectoryCycle
+            | Self::IntegerOverflow => ErrorKind::Format,
+            Self::InvalidFileName | Self::UnknownCodePage { .. } | Self::InvalidText => {
+                ErrorKind::Encoding
+            }
+            Self::DecompressedSizeTooLarge { .. }
+            | Self::HeaderTooLarge
+            | Self::PathCollision { .. } => ErrorKind::Unsupported,
+            Self::HeaderAlreadyRead | Self::HeaderNotRead | Self::ReaderBusy => ErrorKind::State,
+            Self::Cancelled => ErrorKind::Cancelled,
+            Self::NotADir
+            | Self::NotAFile
+            | Self::InvalidPath { .. }
+            | Self::DuplicatePath { .. }
+            | Self::InvalidKeyLength { .. }
+            | Self::InvalidPathComponent { .. }
+            | Self::InvalidKeyHex
+            | Self::NotFound { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+/// A category of [`Error`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An io error
+    Io,
+
+    /// The archive is malformed
+    Format,
+
+    /// A name or text could not be decoded
+    Encoding,
+
+    /// The archive uses a feature or size that is not supported
+    Unsupported,
+
+    /// The reader was used in the wrong state, like before reading the header
+    State,
+
+    /// An argument was invalid, like a path that does not exist
+    InvalidInput,
+
+    /// The caller cancelled the operation
+    Cancelled,
+}
+
+/// Get the encoding for a code page, if it is supported.
+///
+/// Currently, only Shift-JIS (932) and UTF-8 (65001) are supported.
+pub fn encoding_from_code_page(code_page: u64) -> Option<&'static encoding_rs::Encoding> {
+    match code_page {
+        932 => Some(encoding_rs::SHIFT_JIS),
+        65001 => Some(encoding_rs::UTF_8),
+        _ => None,
+    }
 }
 
 /// Create a key from a key string
 fn create_key(key_string: KeyString) -> [u8; KEY_LEN] {
     let mut key = key_string.0;
     key[0] = !key[0];
-    key[1] = (key[1] >> 4) | (key[1] << 4);
+    key[1] = key[1].rotate_left(4);
     key[2] ^= 0x8a;
-    key[3] = !((key[3] >> 4) | (key[3] << 4));
+    key[3] = !key[3].rotate_left(4);
     key[4] = !key[4];
     key[5] ^= 0xac;
     key[6] = !key[6];
-    key[7] = !((key[7] >> 3) | (key[7] << 5));
-    key[8] = (key[8] >> 5) | (key[8] << 3);
+    key[7] = !key[7].rotate_right(3);
+    key[8] = key[8].rotate_left(3);
     key[9] ^= 0x7f;
-    key[10] = ((key[10] >> 4) | (key[10] << 4)) ^ 0xd6;
+    key[10] = key[10].rotate_left(4) ^ 0xd6;
     key[11] ^= 0xcc;
 
     key
 }
 
+/// Create a version 8 key from a key string, which may have a file path appended.
+///
+/// The key is made from the CRC32s of the even and odd bytes of the source.
+fn create_key_v8(source: &[u8]) -> [u8; KEY_LEN_V8] {
+    let even: Vec<u8> = source.iter().step_by(2).copied().collect();
+    let odd: Vec<u8> = source.iter().skip(1).step_by(2).copied().collect();
+
+    let mut key = [0; KEY_LEN_V8];
+    key[..4].copy_from_slice(&crc32(&even).to_le_bytes());
+    key[4..].copy_from_slice(&crc32(&odd).to_le_bytes()[..3]);
+
+    key
+}
+
+/// Calculate the CRC32 (IEEE) of some bytes.
+///
+/// This is only used for short key strings, so it doesn't need a table.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// XOR a buffer with a key, starting at the given position.
+fn key_xor<K>(position: u64, key: K, buffer: &mut [u8])
+where
+    K: AsRef<[u8]>,
+{
+    let key = key.as_ref();
+    let key_len = key.len();
+    // Reduce the position first, so this works with positions that don't fit in a usize.
+    let start = usize::try_from(position % u64::try_from(key_len).unwrap()).unwrap();
+
+    for (i, out_byte) in buffer.iter_mut().enumerate() {
+        let key_byte = key[(start + i) % key_len];
+
+        *out_byte ^= key_byte;
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -114,8 +351,27 @@ mod test {
     #[test]
     fn create_key_works() {
         let key = create_key(DEFAULT_KEY_STRING);
-        dbg!(key);
-
         assert!(key == DEFAULT_KEY);
     }
+
+    #[test]
+    fn crc32_works() {
+        assert!(crc32(b"") == 0);
+        assert!(crc32(b"123456789") == 0xCBF4_3926);
+    }
+
+    #[test]
+    fn error_kind() {
+        assert!(Error::HeaderNotRead.kind() == ErrorKind::State);
+        assert!(Error::InvalidHeader.kind() == ErrorKind::Format);
+        assert!(Error::HeaderTooLarge.kind() == ErrorKind::Unsupported);
+        assert!(Error::InvalidText.kind() == ErrorKind::Encoding);
+        assert!(
+            Error::NotFound {
+                path: String::from("Missing.txt")
+            }
+            .kind()
+                == ErrorKind::InvalidInput
+        );
+    }
 }
@@ -0,0 +1,213 @@
+use crate::ArchiveReader;
+use crate::Error;
+use crate::FileEntry;
+use std::collections::BTreeMap;
+
+/// A reader for multiple archives, layered into one filesystem.
+///
+/// Archives are checked in priority order.
+/// Later archives override earlier ones,
+/// so a file in a later archive hides a file with the same path in an earlier archive.
+/// Like the engine, paths are matched ignoring ASCII case,
+/// so `MAP01.MPS` in a later archive hides `Map01.mps` in an earlier one.
+#[derive(Debug)]
+pub struct MultiArchiveReader<R> {
+    archive_readers: Vec<ArchiveReader<R>>,
+}
+
+impl<R> MultiArchiveReader<R> {
+    /// Create a reader over the given archives.
+    ///
+    /// The headers of every archive must already be read.
+    pub fn new(archive_readers: Vec<ArchiveReader<R>>) -> Self {
+        Self { archive_readers }
+    }
+
+    /// Add an archive, which overrides all archives added before it.
+    ///
+    /// The header of the archive must already be read.
+    pub fn push(&mut self, archive_reader: ArchiveReader<R>) {
+        self.archive_readers.push(archive_reader);
+    }
+
+    /// Get the archives, in priority order.
+    pub fn archive_readers(&self) -> &[ArchiveReader<R>] {
+        &self.archive_readers
+    }
+
+    /// Get a file or dir by its `/`-separated path, relative to the root dir.
+    ///
+    /// This checks each archive, starting with the one with the highest priority, ignoring ASCII case.
+    /// Returns `None` if the path does not exist in any archive.
+    pub fn get_file_by_path(&self, path: &str) -> Result<Option<MultiArchiveEntry<'_, R>>, Error> {
+        for (archive_index, archive_reader) in self.archive_readers.iter().enumerate().rev() {
+            if let Some(file_entry) = archive_reader.get_file_by_path_ci(path)? {
+                return Ok(Some(MultiArchiveEntry {
+                    archive_index,
+                    archive_reader,
+                    file_entry,
+                    path: path.to_string(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Walk over the merged trees of every archive, in order of the upper-case paths.
+    ///
+    /// If a path exists in multiple archives, ignoring ASCII case,
+    /// only the entry from the archive with the highest priority is returned, with its path from that archive.
+    /// The root dirs are not returned.
+    pub fn walk(&self) -> Result<impl Iterator<Item = MultiArchiveEntry<'_, R>>, Error> {
+        let mut entries = BTreeMap::new();
+        for (archive_index, archive_reader) in self.archive_readers.iter().enumerate() {
+            for (path, file_entry) in archive_reader.build_path_index()? {
+                entries.insert(
+                    path.to_ascii_uppercase(),
+                    MultiArchiveEntry {
+                        archive_index,
+                        archive_reader,
+                        file_entry,
+                        path,
+                    },
+                );
+            }
+        }
+
+        Ok(entries.into_values())
+    }
+}
+
+/// A file or dir in a [`MultiArchiveReader`].
+#[derive(Debug)]
+pub struct MultiArchiveEntry<'a, R> {
+    archive_index: usize,
+    archive_reader: &'a ArchiveReader<R>,
+    file_entry: &'a FileEntry,
+    path: String,
+}
+
+impl<'a, R> MultiArchiveEntry<'a, R> {
+    /// Get the index of the archive this entry is from.
+    pub fn archive_index(&self) -> usize {
+        self.archive_index
+    }
+
+    /// Get the archive this entry is from.
+    ///
+    /// Use this to get a file reader for this entry.
+    pub fn archive_reader(&self) -> &'a ArchiveReader<R> {
+        self.archive_reader
+    }
+
+    /// Get the file.
+    ///
+    /// Note that this may be the file data for a dir.
+    pub fn file(&self) -> &'a FileEntry {
+        self.file_entry
+    }
+
+    /// Get the `/`-separated path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ArchiveWriter;
+    use std::io::Cursor;
+    use std::io::Read;
+
+    fn make_archive(files: &[(&str, &[u8])]) -> ArchiveReader<Cursor<Vec<u8>>> {
+        let mut writer = ArchiveWriter::new(Cursor::new(Vec::new()));
+        for (path, data) in files {
+            writer.add_file(path, data).unwrap();
+        }
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut reader = ArchiveReader::from_bytes(archive);
+        reader.read_header().unwrap();
+        reader
+    }
+
+    #[test]
+    fn later_archives_override() {
+        let base = make_archive(&[
+            ("Data/BasicData/Game.dat", b"base"),
+            ("Data/MapData/Map001.mps", b"map"),
+        ]);
+        let patch = make_archive(&[
+            ("Data/BasicData/Game.dat", b"patch"),
+            ("Config.ini", b"[Config]"),
+        ]);
+        let reader = MultiArchiveReader::new(vec![base, patch]);
+
+        let entry = reader
+            .get_file_by_path("Data/BasicData/Game.dat")
+            .unwrap()
+            .unwrap();
+        assert!(entry.archive_index() == 1);
+        let mut data = Vec::new();
+        entry
+            .archive_reader()
+            .get_file_reader(entry.file())
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert!(data == b"patch");
+
+        let entry = reader
+            .get_file_by_path("Data/MapData/Map001.mps")
+            .unwrap()
+            .unwrap();
+        assert!(entry.archive_index() == 0);
+        assert!(reader.get_file_by_path("Missing.txt").unwrap().is_none());
+
+        let paths: Vec<_> = reader
+            .walk()
+            .unwrap()
+            .map(|entry| (entry.path().to_string(), entry.archive_index()))
+            .collect();
+        assert!(
+            paths
+                == [
+                    ("Config.ini".to_string(), 1),
+                    ("Data".to_string(), 1),
+                    ("Data/BasicData".to_string(), 1),
+                    ("Data/BasicData/Game.dat".to_string(), 1),
+                    ("Data/MapData".to_string(), 0),
+                    ("Data/MapData/Map001.mps".to_string(), 0),
+                ]
+        );
+    }
+
+    #[test]
+    fn overrides_ignore_case() {
+        let base = make_archive(&[("Data/MapData/Map01.mps", b"base")]);
+        let patch = make_archive(&[("DATA/MAPDATA/MAP01.MPS", b"patch")]);
+        let reader = MultiArchiveReader::new(vec![base, patch]);
+
+        let entry = reader
+            .get_file_by_path("Data/MapData/Map01.mps")
+            .unwrap()
+            .unwrap();
+        assert!(entry.archive_index() == 1);
+
+        let paths: Vec<_> = reader
+            .walk()
+            .unwrap()
+            .map(|entry| (entry.path().to_string(), entry.archive_index()))
+            .collect();
+        assert!(
+            paths
+                == [
+                    ("DATA".to_string(), 1),
+                    ("DATA/MAPDATA".to_string(), 1),
+                    ("DATA/MAPDATA/MAP01.MPS".to_string(), 1),
+                ]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Compare two names, treating runs of digits as numbers.
+///
+/// This sorts `Map2` before `Map10`, unlike comparing the strings directly.
+/// Numbers that are equal but have a different number of leading zeros are ordered by the text after them,
+/// and then with fewer leading zeros first, so this is a total order.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    // Used to break ties between numbers like "01" and "1" if nothing else differs.
+    let mut zeros_ordering = Ordering::Equal;
+
+    loop {
+        let (a_char, b_char) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return zeros_ordering,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) => (*a_char, *b_char),
+        };
+
+        if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let (a_digits, a_zeros) = take_number(&mut a_chars);
+            let (b_digits, b_zeros) = take_number(&mut b_chars);
+
+            // Without leading zeros, a longer number is always larger.
+            let ordering = a_digits
+                .len()
+                .cmp(&b_digits.len())
+                .then_with(|| a_digits.cmp(&b_digits));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            if zeros_ordering == Ordering::Equal {
+                zeros_ordering = a_zeros.cmp(&b_zeros);
+            }
+        } else {
+            let ordering = a_char.cmp(&b_char);
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            a_chars.next();
+            b_chars.next();
+        }
+    }
+}
+
+/// Compare two `/`-separated paths, comparing each component with [`natural_cmp`].
+///
+/// This keeps the entries of a dir together, as a dir is always sorted before its children.
+pub fn natural_path_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_components = a.split('/');
+    let mut b_components = b.split('/');
+
+    loop {
+        match (a_components.next(), b_components.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_component), Some(b_component)) => {
+                let ordering = natural_cmp(a_component, b_component);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+/// Take a run of ASCII digits, returning the digits without leading zeros and the number of leading zeros.
+fn take_number(chars: &mut Peekable<Chars<'_>>) -> (String, usize) {
+    let mut digits = String::new();
+    let mut zeros = 0;
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        if c == '0' && digits.is_empty() {
+            zeros += 1;
+        } else {
+            digits.push(c);
+        }
+    }
+
+    (digits, zeros)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_works() {
+        let mut names = vec![
+            "Map100.mps",
+            "Map10.mps",
+            "Map2.mps",
+            "Map1.mps",
+            "Map02.mps",
+            "Map.mps",
+            "Pic1.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert!(
+            names
+                == [
+                    "Map.mps",
+                    "Map1.mps",
+                    "Map2.mps",
+                    "Map02.mps",
+                    "Map10.mps",
+                    "Map100.mps",
+                    "Pic1.png",
+                ]
+        );
+
+        assert!(natural_cmp("a1b", "a01a") == Ordering::Greater);
+        assert!(natural_cmp("0", "00") == Ordering::Less);
+        assert!(natural_cmp("Map001", "Map1") == Ordering::Greater);
+        assert!(natural_cmp("", "") == Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_path_cmp_works() {
+        let mut paths = vec![
+            "Data/Map10/A.mps",
+            "Data/Map2",
+            "Data/Map2/B.mps",
+            "Data/Map10",
+            "Data",
+        ];
+        paths.sort_by(|a, b| natural_path_cmp(a, b));
+        assert!(
+            paths
+                == [
+                    "Data",
+                    "Data/Map2",
+                    "Data/Map2/B.mps",
+                    "Data/Map10",
+                    "Data/Map10/A.mps",
+                ]
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::Error;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Join archive path components into a relative path that is safe to join onto an output dir.
+///
+/// This rejects components that are empty, refer to the current or parent dir,
+/// contain separators, drive prefixes, or nul bytes,
+/// or would otherwise not be a single normal path component.
+pub fn sanitize_components<S>(components: &[S]) -> Result<PathBuf, Error>
+where
+    S: AsRef<str>,
+{
+    let mut path = PathBuf::new();
+    for component in components {
+        let component = component.as_ref();
+
+        let is_suspicious = component.is_empty()
+            || component == "."
+            || component == ".."
+            || component.contains(['/', '\\', ':', '\0']);
+
+        // Each component must be exactly one normal component on this platform.
+        let mut path_components = Path::new(component).components();
+        let is_normal = matches!(
+            (path_components.next(), path_components.next()),
+            (Some(Component::Normal(_)), None)
+        );
+
+        if is_suspicious || !is_normal {
+            return Err(Error::InvalidPathComponent {
+                component: component.to_string(),
+            });
+        }
+
+        path.push(component);
+    }
+
+    Ok(path)
+}
+
+/// Find paths that would be extracted to the same path on a case-insensitive filesystem.
+///
+/// Paths are compared ignoring case, so `Foo.png` and `foo.png` collide.
+/// This returns the indexes of each colliding pair as `(first, second)`,
+/// where `first` is the earliest path that `second` collides with.
+/// Pairs are in the order of `second`.
+pub fn find_path_collisions<I, P>(paths: I) -> Vec<(usize, usize)>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut first_indexes = HashMap::new();
+    let mut collisions = Vec::new();
+    for (index, path) in paths.into_iter().enumerate() {
+        let key = path.as_ref().to_string_lossy().to_lowercase();
+        match first_indexes.entry(key) {
+            Entry::Occupied(entry) => collisions.push((*entry.get(), index)),
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+
+    collisions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sanitize() {
+        let path = sanitize_components(&["Data", "BasicData", "Game.dat"]).unwrap();
+        assert!(path == Path::new("Data").join("BasicData").join("Game.dat"));
+
+        for component in [
+            "..", ".", "", "C:\\", "C:", "a/../b", "..\\b", "/etc", "a\0b",
+        ] {
+            assert!(
+                matches!(
+                    sanitize_components(&["Data", component]),
+                    Err(Error::InvalidPathComponent { .. })
+                ),
+                "\"{}\" was not rejected",
+                component.escape_debug()
+            );
+        }
+    }
+
+    #[test]
+    fn path_collisions() {
+        let paths = [
+            "Foo.png",
+            "Data/a.txt",
+            "foo.png",
+            "Bar.png",
+            "FOO.PNG",
+            "Data/A.txt",
+        ];
+        assert!(find_path_collisions(paths) == [(0, 2), (0, 4), (1, 5)]);
+        assert!(find_path_collisions(["Foo.png", "Bar.png"]).is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A handle to a file that can be cloned, where each clone has its own position.
+///
+/// Clones share the same open file, so they don't use any more file descriptors.
+/// Reads use the position of the handle instead of the position of the file,
+/// so clones can be read at the same time, even from different threads.
+/// This makes it suitable for [`ArchiveReader::get_file_reader_owned`](crate::ArchiveReader::get_file_reader_owned).
+#[derive(Debug, Clone)]
+pub struct SharedFile {
+    file: Arc<File>,
+    position: u64,
+}
+
+impl SharedFile {
+    /// Make a new handle from a file, starting at position 0.
+    pub fn new(file: File) -> Self {
+        Self {
+            file: Arc::new(file),
+            position: 0,
+        }
+    }
+
+    /// Open a file in read-only mode.
+    pub fn open<P>(path: P) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(File::open(path)?))
+    }
+}
+
+impl Read for SharedFile {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(&*self.file, buffer, self.position)?;
+
+        // This moves the position of the file, but it is never used.
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(&*self.file, buffer, self.position)?;
+
+        self.position += u64::try_from(n).unwrap();
+
+        Ok(n)
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let (base, delta) = match position {
+            SeekFrom::Start(offset) => (offset, 0),
+            SeekFrom::End(delta) => (self.file.metadata()?.len(), delta),
+            SeekFrom::Current(delta) => (self.position, delta),
+        };
+
+        self.position = base.checked_add_signed(delta).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clones_have_own_position() {
+        let path = std::env::temp_dir().join("wolf-rpg-data-shared-file");
+        std::fs::write(&path, b"0123456789").unwrap();
+        let mut file = SharedFile::open(&path).unwrap();
+
+        let mut clone = file.clone();
+        clone.seek(SeekFrom::End(-4)).unwrap();
+
+        let mut buffer = [0; 3];
+        file.read_exact(&mut buffer).unwrap();
+        assert!(buffer == *b"012");
+        clone.read_exact(&mut buffer).unwrap();
+        assert!(buffer == *b"678");
+        file.read_exact(&mut buffer).unwrap();
+        assert!(buffer == *b"345");
+
+        assert!(clone.stream_position().unwrap() == 9);
+        assert!(clone.seek(SeekFrom::Current(-10)).is_err());
+
+        drop((file, clone));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+use crate::util::open_archive;
+use anyhow::Context;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "print a file from an archive to stdout")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(help = "the path of the file in the archive, like \"Data/BasicData/Game.dat\"")]
+    pub path: String,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let mut stdout = std::io::stdout().lock();
+    reader
+        .extract_file_to(&options.path, &mut stdout)
+        .with_context(|| format!("failed to read \"{}\"", options.path))?;
+
+    Ok(())
+}
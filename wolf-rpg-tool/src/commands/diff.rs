@@ -0,0 +1,61 @@
+use crate::util::hash_file;
+use crate::util::open_archive;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "list the files that were added, removed, or modified between two archives")]
+pub struct Options {
+    #[arg(help = "the path to the old archive")]
+    pub old: PathBuf,
+
+    #[arg(help = "the path to the new archive")]
+    pub new: PathBuf,
+
+    #[arg(
+        long,
+        help = "compare files by a hash of their contents, instead of by size and modified time"
+    )]
+    pub hash: bool,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let old_reader = open_archive(&options.old)?;
+    let new_reader = open_archive(&options.new)?;
+
+    let old_index = old_reader.build_path_index()?;
+    let new_index = new_reader.build_path_index()?;
+
+    let paths: BTreeSet<&String> = old_index.keys().chain(new_index.keys()).collect();
+
+    let mut stdout = std::io::stdout().lock();
+    for path in paths {
+        let old_file = old_index.get(path).filter(|file| !file.is_dir());
+        let new_file = new_index.get(path).filter(|file| !file.is_dir());
+
+        let status = match (old_file, new_file) {
+            (None, None) => continue,
+            (None, Some(_)) => 'A',
+            (Some(_), None) => 'D',
+            (Some(old_file), Some(new_file)) => {
+                let is_modified = if options.hash {
+                    old_file.size() != new_file.size()
+                        || hash_file(&old_reader, old_file)? != hash_file(&new_reader, new_file)?
+                } else {
+                    old_file.size() != new_file.size()
+                        || old_file.file_times().modified() != new_file.file_times().modified()
+                };
+                if !is_modified {
+                    continue;
+                }
+
+                'M'
+            }
+        };
+
+        writeln!(stdout, "{status} {path}")?;
+    }
+
+    Ok(())
+}
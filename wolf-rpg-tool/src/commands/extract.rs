@@ -0,0 +1,426 @@
+use crate::glob::Glob;
+use crate::output::HashRecord;
+use crate::util::format_hex;
+use crate::util::open_archive;
+use anyhow::Context;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use rayon::iter::IntoParallelRefIterator;
+use rayon::iter::ParallelIterator;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use wolf_rpg_data::find_path_collisions;
+use wolf_rpg_data::sanitize_components;
+use wolf_rpg_data::ArchiveReader;
+use wolf_rpg_data::FileEntry;
+use wolf_rpg_data::FileTimes;
+use wolf_rpg_data::SharedFile;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "extract an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(default_value = "out", help = "the dir to extract to")]
+    pub output: PathBuf,
+
+    #[arg(
+        long,
+        help = "only extract paths matching this glob. May be given more than once."
+    )]
+    pub include: Vec<Glob>,
+
+    #[arg(
+        long,
+        help = "skip paths matching this glob. May be given more than once."
+    )]
+    pub exclude: Vec<Glob>,
+
+    #[arg(long, help = "skip files that already exist in the output dir")]
+    pub skip_existing: bool,
+
+    #[arg(
+        long,
+        help = "skip files whose copy in the output dir is at least as new as the archive's"
+    )]
+    pub newer_only: bool,
+
+    #[arg(
+        short,
+        long,
+        default_value_t = 1,
+        help = "the number of files to extract at once"
+    )]
+    pub jobs: usize,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "remove this many leading components from each path, skipping entries with fewer components"
+    )]
+    pub strip_components: usize,
+
+    #[arg(
+        long,
+        default_value = "error",
+        help = "what to do when two files would be extracted to the same path on a case-insensitive filesystem: \"error\" or \"rename\""
+    )]
+    pub on_collision: OnCollision,
+
+    #[arg(
+        long,
+        help = "write a JSON manifest with the path relative to the output dir, size, and SHA-256 of every extracted file to this path"
+    )]
+    pub manifest: Option<PathBuf>,
+}
+
+/// What to do when two files would be extracted to the same path.
+#[derive(Debug, Copy, Clone)]
+pub enum OnCollision {
+    /// Fail before extracting anything.
+    Error,
+
+    /// Add a numbered suffix to the later file.
+    Rename,
+}
+
+impl FromStr for OnCollision {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "error" => Ok(Self::Error),
+            "rename" => Ok(Self::Rename),
+            _ => Err(format!(
+                "unknown collision mode \"{value}\", expected \"error\" or \"rename\""
+            )),
+        }
+    }
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    std::fs::create_dir_all(&options.output)?;
+
+    // Paths come from the dir tree, so files in an archive without one can't be placed.
+    if reader.get_root_dir()?.is_none() {
+        log::warn!("the archive has no root dir, so there is nothing to extract");
+    }
+
+    let mut entries = Vec::new();
+    for entry in reader.walk()? {
+        let entry = entry?;
+
+        let path = entry.path_components().join("/");
+        if !path.is_empty() && !is_path_selected(&options, &path) {
+            continue;
+        }
+
+        let Some(stripped_path_components) =
+            entry.path_components().get(options.strip_components..)
+        else {
+            continue;
+        };
+        // A file with exactly as many components as are stripped would be written over the output dir.
+        if stripped_path_components.is_empty() && !entry.file().is_dir() {
+            continue;
+        }
+        let output = options
+            .output
+            .join(sanitize_components(stripped_path_components)?);
+
+        entries.push((entry, output));
+    }
+
+    // Files whose paths only differ by case would overwrite each other on case-insensitive filesystems.
+    // Renamed files may collide again, so this repeats until there are no collisions.
+    let mut files: Vec<_> = entries
+        .iter_mut()
+        .filter(|(entry, _output)| !entry.file().is_dir())
+        .map(|(entry, output)| (entry.path_components().join("/"), output.clone(), output))
+        .collect();
+    let mut renames = vec![None; files.len()];
+    loop {
+        let collisions =
+            find_path_collisions(files.iter().map(|(_path, _original, output)| output));
+        if collisions.is_empty() {
+            break;
+        }
+
+        for (first, second) in collisions {
+            if let OnCollision::Error = options.on_collision {
+                return Err(wolf_rpg_data::Error::PathCollision {
+                    first: files[first].0.clone(),
+                    second: files[second].0.clone(),
+                }
+                .into());
+            }
+
+            // Files that keep their names win over renamed files.
+            let (index, other) = if renames[first].is_some() && renames[second].is_none() {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            let other_path = files[other].0.clone();
+            let (_path, original, output) = &mut files[index];
+            let (_other_path, n) = renames[index].get_or_insert((other_path, 0));
+            *n += 1;
+            **output = add_path_suffix(original, *n);
+        }
+    }
+    for ((path, _original, output), rename) in files.iter().zip(renames) {
+        if let Some((other_path, _n)) = rename {
+            log::warn!(
+                "\"{path}\" collides with \"{other_path}\", extracting it to \"{}\"",
+                output.display()
+            );
+        }
+    }
+
+    let total_size = entries
+        .iter()
+        .map(|(entry, _output)| entry.file())
+        .filter(|file| !file.is_dir())
+        .map(|file| file.size())
+        .sum();
+    // Quiet mode only shows errors, so the progress bar is hidden too.
+    let progress_bar = if log::max_level() >= log::LevelFilter::Warn {
+        ProgressBar::new(total_size)
+    } else {
+        ProgressBar::hidden()
+    };
+    progress_bar.set_style(
+        ProgressStyle::with_template(
+            "{wide_bar} {bytes}/{total_bytes} [{elapsed_precise}<{eta_precise}]",
+        )
+        .expect("invalid progress bar template"),
+    );
+
+    // Dirs are created up front, so that files can be written in any order.
+    let mut files = Vec::new();
+    for (entry, output) in entries {
+        let file = entry.file();
+
+        if file.is_dir() {
+            std::fs::create_dir_all(output)?;
+        } else {
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            files.push((entry.path_components().join("/"), file, output));
+        }
+    }
+
+    let extract = |(path, file, output): &(String, &FileEntry, PathBuf)| {
+        extract_file(&options, &reader, path, file, output, &progress_bar)
+    };
+    let hash_records = if options.jobs > 1 {
+        // Each file reader owns a handle to the archive with its own position,
+        // so the threads can share this archive reader.
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(options.jobs)
+            .build()?;
+        thread_pool.install(|| {
+            files
+                .par_iter()
+                .map(extract)
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?
+    } else {
+        files
+            .iter()
+            .map(extract)
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    progress_bar.finish();
+
+    if let Some(manifest) = options.manifest.as_ref() {
+        let hash_records: Vec<_> = hash_records.into_iter().flatten().collect();
+        write_manifest(manifest, &hash_records)
+            .with_context(|| format!("failed to write \"{}\"", manifest.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Write a hash manifest as JSON.
+fn write_manifest(path: &Path, hash_records: &[HashRecord]) -> anyhow::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut file, hash_records)?;
+    writeln!(file)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+/// Extract a single file.
+///
+/// If a manifest was requested and the file was not skipped, this returns its hash record.
+fn extract_file(
+    options: &Options,
+    reader: &ArchiveReader<SharedFile>,
+    path: &str,
+    file: &FileEntry,
+    output: &Path,
+    progress_bar: &ProgressBar,
+) -> anyhow::Result<Option<HashRecord>> {
+    if should_skip_file(options, file.file_times(), output)? {
+        progress_bar.inc(file.size());
+        return Ok(None);
+    }
+
+    if log::log_enabled!(log::Level::Info) {
+        progress_bar.suspend(|| log::info!("extracting \"{}\"", output.display()));
+    }
+
+    let mut reader = reader.get_file_reader_owned(file)?;
+
+    let mut output_file = File::create(output)?;
+    let mut writer = HashingWriter {
+        writer: progress_bar.wrap_write(&mut output_file),
+        hasher: options.manifest.is_some().then(Sha256::new),
+    };
+    let size = std::io::copy(&mut reader, &mut writer)?;
+    let hasher = writer.hasher;
+    output_file.set_times(get_std_file_times(file.file_times()))?;
+
+    let Some(hasher) = hasher else {
+        return Ok(None);
+    };
+
+    // The manifest records where the file was written, which may differ from its archive path,
+    // so that it can be checked against the output dir later.
+    let relative_output = output
+        .strip_prefix(&options.output)
+        .with_context(|| format!("\"{path}\" was extracted outside of the output dir"))?;
+    let relative_output: Vec<_> = relative_output
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect();
+
+    Ok(Some(HashRecord {
+        path: relative_output.join("/"),
+        size,
+        sha256: format_hex(&hasher.finalize()),
+    }))
+}
+
+/// A writer that can hash everything written through it.
+struct HashingWriter<W> {
+    writer: W,
+    hasher: Option<Sha256>,
+}
+
+impl<W> Write for HashingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let n = self.writer.write(buffer)?;
+        if let Some(hasher) = self.hasher.as_mut() {
+            hasher.update(&buffer[..n]);
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Add a numbered suffix to the file name of a path, before the extension, like `Foo (1).png`.
+fn add_path_suffix(path: &Path, n: u32) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!(" ({n})"));
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    path.with_file_name(file_name)
+}
+
+/// Check a path against the include and exclude globs.
+///
+/// Excludes always win.
+/// If no includes are given, everything is included.
+fn is_path_selected(options: &Options, path: &str) -> bool {
+    if options.exclude.iter().any(|glob| glob.is_match(path)) {
+        return false;
+    }
+
+    options.include.is_empty() || options.include.iter().any(|glob| glob.is_match(path))
+}
+
+/// Check if a file should be skipped, given where it would be extracted to.
+fn should_skip_file(
+    options: &Options,
+    file_times: FileTimes,
+    output: &Path,
+) -> anyhow::Result<bool> {
+    if !options.skip_existing && !options.newer_only {
+        return Ok(false);
+    }
+
+    let metadata = match std::fs::metadata(output) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => {
+            return Err(error).with_context(|| format!("failed to stat \"{}\"", output.display()))
+        }
+    };
+
+    if options.skip_existing {
+        return Ok(true);
+    }
+
+    // If either time is missing, we can't tell which is newer.
+    let (Some(archive_modified), Ok(output_modified)) =
+        (file_times.modified(), metadata.modified())
+    else {
+        return Ok(false);
+    };
+
+    Ok(output_modified >= archive_modified)
+}
+
+/// Convert the file times of an archive entry into file times that can be set on a file.
+///
+/// Times that are not valid are left unset, keeping the OS default.
+fn get_std_file_times(file_times: FileTimes) -> std::fs::FileTimes {
+    let mut std_file_times = std::fs::FileTimes::new();
+    if let Some(modified) = file_times.modified() {
+        std_file_times = std_file_times.set_modified(modified);
+    }
+    if let Some(accessed) = file_times.accessed() {
+        std_file_times = std_file_times.set_accessed(accessed);
+    }
+
+    #[cfg(windows)]
+    if let Some(created) = file_times.created() {
+        use std::os::windows::fs::FileTimesExt;
+
+        std_file_times = std_file_times.set_created(created);
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Some(created) = file_times.created() {
+        use std::os::macos::fs::FileTimesExt;
+
+        std_file_times = std_file_times.set_created(created);
+    }
+
+    std_file_times
+}
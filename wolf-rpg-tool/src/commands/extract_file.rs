@@ -0,0 +1,41 @@
+use crate::util::open_archive;
+use anyhow::bail;
+use anyhow::Context;
+use std::fs::File;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "extract a single file from an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(help = "the path of the file in the archive, like \"Data/BasicData/Game.dat\"")]
+    pub path: String,
+
+    #[arg(help = "the path to write the file to, or \"-\" for stdout")]
+    pub output: PathBuf,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let file = reader
+        .get_file_by_path(&options.path)?
+        .with_context(|| format!("\"{}\" does not exist in the archive", options.path))?;
+    if file.is_dir() {
+        bail!("\"{}\" is a directory", options.path);
+    }
+
+    let mut file_reader = reader.get_file_reader(file)?;
+    if options.output.as_os_str() == "-" {
+        let mut stdout = std::io::stdout().lock();
+        std::io::copy(&mut file_reader, &mut stdout)?;
+    } else {
+        let mut output = File::create(&options.output)
+            .with_context(|| format!("failed to create \"{}\"", options.output.display()))?;
+        std::io::copy(&mut file_reader, &mut output)?;
+    }
+
+    Ok(())
+}
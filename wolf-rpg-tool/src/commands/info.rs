@@ -0,0 +1,29 @@
+use crate::util::open_archive;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "print a summary of an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+    let stats = reader.stats()?;
+
+    println!("Version: {}", reader.version()?);
+    println!("Code Page: {}", reader.code_page()?);
+    println!("Encoding: {}", reader.encoding_name());
+    println!("Data Position: {}", reader.data_position()?);
+    println!("Files: {}", stats.file_count);
+    println!("Dirs: {}", stats.dir_count);
+    println!("Total Size: {} bytes", stats.uncompressed_size);
+    println!("Compressed Size: {} bytes", stats.compressed_size);
+    println!(
+        "Compression Ratio: {:.2}%",
+        stats.compression_ratio() * 100.0
+    );
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::output::write_csv;
+use crate::output::write_json;
+use crate::output::EntryRecord;
+use crate::output::OutputFormat;
+use crate::util::format_size;
+use crate::util::open_archive;
+use chrono::DateTime;
+use chrono::Utc;
+use std::cmp::Reverse;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use wolf_rpg_data::natural_path_cmp;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "list the entries of an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "show the size, compressed size, compression ratio, and modified time of each entry"
+    )]
+    pub long: bool,
+
+    #[arg(long, help = "show sizes in bytes, instead of human-readable units")]
+    pub bytes: bool,
+
+    #[arg(
+        long,
+        help = "sort entries by \"name\", \"natural\" (numbers in names by value), \"size\" (largest first), or \"date\" (newest first). Defaults to archive order."
+    )]
+    pub sort: Option<SortKey>,
+
+    #[arg(
+        long,
+        default_value = "text",
+        help = "the output format: \"text\", \"csv\", or \"json\""
+    )]
+    pub format: OutputFormat,
+}
+
+/// What to sort the listing by.
+#[derive(Debug, Copy, Clone)]
+pub enum SortKey {
+    Name,
+    Natural,
+    Size,
+    Date,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(Self::Name),
+            "natural" => Ok(Self::Natural),
+            "size" => Ok(Self::Size),
+            "date" => Ok(Self::Date),
+            _ => Err(format!(
+                "unknown sort key \"{value}\", expected \"name\", \"natural\", \"size\", or \"date\""
+            )),
+        }
+    }
+}
+
+/// A row of the listing.
+struct Row {
+    path: String,
+    size: Option<u64>,
+    compressed_size: Option<u64>,
+    modified: Option<DateTime<Utc>>,
+    record: EntryRecord,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let mut rows = Vec::new();
+    for entry in reader.walk()? {
+        let entry = entry?;
+        let path_components = entry.path_components();
+        if path_components.is_empty() {
+            continue;
+        }
+        let file = entry.file();
+
+        let record = EntryRecord::new(path_components.join("/"), file);
+        let mut path = record.path.clone();
+        if file.is_dir() {
+            path.push('/');
+        }
+
+        let (size, compressed_size) = if file.is_dir() {
+            (None, None)
+        } else {
+            (Some(file.size()), file.compressed_size())
+        };
+
+        rows.push(Row {
+            path,
+            size,
+            compressed_size,
+            modified: file.file_times().modified_datetime(),
+            record,
+        });
+    }
+
+    match options.sort {
+        // Paths are compared by component, like natural_path_cmp, so that a dir's entries stay together.
+        Some(SortKey::Name) => {
+            rows.sort_by(|a, b| a.record.path.split('/').cmp(b.record.path.split('/')))
+        }
+        Some(SortKey::Natural) => {
+            rows.sort_by(|a, b| natural_path_cmp(&a.record.path, &b.record.path))
+        }
+        Some(SortKey::Size) => rows.sort_by_key(|row| Reverse(row.size)),
+        Some(SortKey::Date) => rows.sort_by_key(|row| Reverse(row.modified)),
+        None => {}
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    match options.format {
+        OutputFormat::Text => {}
+        OutputFormat::Csv => return write_csv(stdout, &into_records(rows)),
+        OutputFormat::Json => return write_json(stdout, &into_records(rows)),
+    }
+
+    if !options.long {
+        for row in rows.iter() {
+            writeln!(stdout, "{}", row.path)?;
+        }
+
+        return Ok(());
+    }
+
+    let format_size = |size: Option<u64>| match size {
+        Some(size) if options.bytes => size.to_string(),
+        Some(size) => format_size(size),
+        None => String::from("-"),
+    };
+    let columns: Vec<[String; 4]> = rows
+        .iter()
+        .map(|row| {
+            let ratio = match (row.size, row.compressed_size) {
+                (Some(size), Some(compressed_size)) if size != 0 => {
+                    format!("{:.1}%", (compressed_size as f64 / size as f64) * 100.0)
+                }
+                _ => String::from("-"),
+            };
+            let modified = row
+                .modified
+                .map(|modified| modified.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| String::from("-"));
+
+            [
+                format_size(row.size),
+                format_size(row.compressed_size),
+                ratio,
+                modified,
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 4];
+    for column in columns.iter() {
+        for (width, value) in widths.iter_mut().zip(column.iter()) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let [size_width, compressed_size_width, ratio_width, modified_width] = widths;
+    for (row, [size, compressed_size, ratio, modified]) in rows.iter().zip(columns.iter()) {
+        writeln!(
+            stdout,
+            "{size:>size_width$}  {compressed_size:>compressed_size_width$}  {ratio:>ratio_width$}  {modified:<modified_width$}  {}",
+            row.path
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Get the records of rows, for machine-readable output.
+fn into_records(rows: Vec<Row>) -> Vec<EntryRecord> {
+    rows.into_iter().map(|row| row.record).collect()
+}
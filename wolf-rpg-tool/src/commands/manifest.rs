@@ -0,0 +1,72 @@
+use crate::output::write_csv;
+use crate::output::write_json;
+use crate::output::EntryRecord;
+use crate::output::OutputFormat;
+use crate::util::open_archive;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "print a manifest of every entry in an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(long, help = "include the CRC32 of each file. This reads every file.")]
+    pub crc32: bool,
+
+    #[arg(
+        long,
+        default_value = "text",
+        help = "the output format: \"text\", \"csv\", or \"json\""
+    )]
+    pub format: OutputFormat,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let mut manifest = Vec::new();
+    for entry in reader.walk()? {
+        let entry = entry?;
+        let path_components = entry.path_components();
+        if path_components.is_empty() {
+            continue;
+        }
+        let file = entry.file();
+
+        let mut record = EntryRecord::new(path_components.join("/"), file);
+        if options.crc32 && !file.is_dir() {
+            let (_data, crc32) = reader.get_file_reader(file)?.read_to_end_with_crc32()?;
+            record.crc32 = Some(crc32);
+        }
+
+        manifest.push(record);
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    match options.format {
+        OutputFormat::Text => {
+            for record in manifest.iter() {
+                let kind = if record.is_dir { 'd' } else { 'f' };
+                write!(
+                    stdout,
+                    "{kind} {:08X} {:>12}",
+                    record.attributes.bits(),
+                    record.size
+                )?;
+                if options.crc32 {
+                    match record.crc32 {
+                        Some(crc32) => write!(stdout, " {crc32:08X}")?,
+                        None => write!(stdout, " {:8}", "-")?,
+                    }
+                }
+                writeln!(stdout, " {}", record.path)?;
+            }
+        }
+        OutputFormat::Csv => write_csv(stdout, &manifest)?,
+        OutputFormat::Json => write_json(stdout, &manifest)?,
+    }
+
+    Ok(())
+}
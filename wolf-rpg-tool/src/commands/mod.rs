@@ -0,0 +1,12 @@
+pub mod cat;
+pub mod diff;
+pub mod extract;
+pub mod extract_file;
+pub mod info;
+pub mod list;
+pub mod manifest;
+pub mod pack;
+pub mod search;
+pub mod tar;
+pub mod tree;
+pub mod verify;
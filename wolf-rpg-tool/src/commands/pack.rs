@@ -0,0 +1,109 @@
+use anyhow::Context;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use wolf_rpg_data::ArchiveWriter;
+use wolf_rpg_data::FileTimes;
+use wolf_rpg_data::KeyString;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "build an archive from a dir")]
+pub struct Options {
+    #[arg(help = "the dir to pack")]
+    pub input: PathBuf,
+
+    #[arg(help = "the path to write the archive to")]
+    pub output: PathBuf,
+
+    #[arg(long, help = "store every file without compression")]
+    pub no_compress: bool,
+
+    /// The key string to encrypt the archive with, from the global `--key` option.
+    #[arg(skip)]
+    pub key: Option<KeyString>,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let output_file = File::create(&options.output)
+        .with_context(|| format!("failed to create \"{}\"", options.output.display()))?;
+
+    let mut writer =
+        ArchiveWriter::new(BufWriter::new(output_file)).with_compression(!options.no_compress);
+    if let Some(key_string) = options.key {
+        writer = writer.with_key_string(key_string);
+    }
+
+    pack_dir(&mut writer, &options.input, "")?;
+
+    let mut output_file = writer.finish()?;
+    output_file.flush()?;
+
+    Ok(())
+}
+
+/// Add the contents of a dir to an archive, recursively.
+///
+/// `prefix` is the path of the dir in the archive.
+fn pack_dir(
+    writer: &mut ArchiveWriter<BufWriter<File>>,
+    dir: &Path,
+    prefix: &str,
+) -> anyhow::Result<()> {
+    let mut dir_entries = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read \"{}\"", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()?;
+    // Sort for reproducible archives, as the order of read_dir is unspecified.
+    dir_entries.sort_by_key(|dir_entry| dir_entry.file_name());
+
+    for dir_entry in dir_entries {
+        let path = dir_entry.path();
+        let file_name = dir_entry.file_name();
+        let file_name = file_name
+            .to_str()
+            .with_context(|| format!("\"{}\" is not valid unicode", path.display()))?;
+        let archive_path = if prefix.is_empty() {
+            file_name.to_string()
+        } else {
+            format!("{prefix}/{file_name}")
+        };
+
+        let metadata = std::fs::metadata(&path)
+            .with_context(|| format!("failed to stat \"{}\"", path.display()))?;
+        let file_times = get_file_times(&metadata);
+
+        if metadata.is_dir() {
+            writer.add_dir_with_times(&archive_path, file_times)?;
+            pack_dir(writer, &path, &archive_path)?;
+        } else {
+            log::info!("packing \"{archive_path}\"");
+
+            let data = std::fs::read(&path)
+                .with_context(|| format!("failed to read \"{}\"", path.display()))?;
+            writer
+                .add_file_with_times(&archive_path, &data, file_times)
+                .with_context(|| format!("failed to add \"{archive_path}\""))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the file times of a file on disk.
+///
+/// Times that are not available or can't be stored in an archive are left unset.
+fn get_file_times(metadata: &std::fs::Metadata) -> FileTimes {
+    let mut file_times = FileTimes::default();
+    if let Ok(modified) = metadata.modified() {
+        file_times.set_modified(modified);
+    }
+    if let Ok(accessed) = metadata.accessed() {
+        file_times.set_accessed(accessed);
+    }
+    if let Ok(created) = metadata.created() {
+        file_times.set_created(created);
+    }
+
+    file_times
+}
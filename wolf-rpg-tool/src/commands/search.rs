@@ -0,0 +1,54 @@
+use crate::util::open_archive;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "print the paths of entries whose name contains a string")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(help = "the string to search for")]
+    pub query: String,
+
+    #[arg(long, help = "ignore ASCII case when matching")]
+    pub ci: bool,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    // The archive stores an upper-case copy of every name, so case-insensitive matching only needs to upper-case the query.
+    let query = if options.ci {
+        options.query.to_ascii_uppercase()
+    } else {
+        options.query.clone()
+    };
+
+    let mut stdout = std::io::stdout().lock();
+    for entry in reader.walk()? {
+        let entry = entry?;
+        let path_components = entry.path_components();
+        if path_components.is_empty() {
+            continue;
+        }
+        let file = entry.file();
+
+        let name = if options.ci {
+            reader.get_upper_file_name(file)?
+        } else {
+            reader.get_file_name(file)?
+        };
+        if !name.contains(&query) {
+            continue;
+        }
+
+        let mut path = path_components.join("/");
+        if file.is_dir() {
+            path.push('/');
+        }
+        writeln!(stdout, "{path}")?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use crate::util::open_archive;
+use anyhow::Context;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "convert an archive into a tar file")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(help = "the path to the tar file to create")]
+    pub output: PathBuf,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let output = File::create(&options.output)
+        .with_context(|| format!("failed to create \"{}\"", options.output.display()))?;
+    let mut output = reader.to_tar(BufWriter::new(output))?;
+    output.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use crate::util::open_archive;
+use anyhow::Context;
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use wolf_rpg_data::natural_cmp;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "print the dir hierarchy of an archive")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(
+        long,
+        default_value = "name",
+        help = "sort entries by \"name\" or \"natural\" (numbers in names by value, so \"Map2\" comes before \"Map10\")"
+    )]
+    pub sort: SortKey,
+}
+
+/// What to sort the entries of each dir by.
+///
+/// Dirs always come first.
+#[derive(Debug, Copy, Clone)]
+pub enum SortKey {
+    Name,
+    Natural,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(Self::Name),
+            "natural" => Ok(Self::Natural),
+            _ => Err(format!(
+                "unknown sort key \"{value}\", expected \"name\" or \"natural\""
+            )),
+        }
+    }
+}
+
+/// A file or dir in the tree.
+#[derive(Debug)]
+struct Node<'a> {
+    name: &'a str,
+    is_dir: bool,
+    children: Vec<Node<'a>>,
+}
+
+impl Node<'_> {
+    /// Sort the children of this node and all of its descendants, dirs first.
+    fn sort(&mut self, sort_key: SortKey) {
+        self.children.sort_by(|a, b| {
+            b.is_dir.cmp(&a.is_dir).then_with(|| match sort_key {
+                SortKey::Name => a.name.cmp(b.name),
+                SortKey::Natural => natural_cmp(a.name, b.name),
+            })
+        });
+        for child in self.children.iter_mut() {
+            child.sort(sort_key);
+        }
+    }
+
+    /// Write the children of this node, prefixing each line with the given prefix.
+    fn write_children<W>(&self, writer: &mut W, prefix: &mut String) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        for (i, child) in self.children.iter().enumerate() {
+            let is_last = i + 1 == self.children.len();
+            let (branch, indent) = if is_last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            writeln!(writer, "{prefix}{branch}{}", child.name)?;
+
+            let prefix_len = prefix.len();
+            prefix.push_str(indent);
+            child.write_children(writer, prefix)?;
+            prefix.truncate(prefix_len);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+
+    let root_dir = reader.get_root_dir()?.context("no root dir")?;
+
+    // The walk is depth-first, so the stack always holds the ancestors of the next entry.
+    let mut stack: Vec<Node> = Vec::new();
+    for entry in reader.walk_dir(root_dir)? {
+        let entry = entry?;
+        let depth = entry.depth();
+
+        while stack.len() > depth {
+            let node = stack.pop().unwrap();
+            stack
+                .last_mut()
+                .context("missing parent dir")?
+                .children
+                .push(node);
+        }
+
+        stack.push(Node {
+            name: entry.path_components().last().copied().unwrap_or_default(),
+            is_dir: entry.file().is_dir(),
+            children: Vec::new(),
+        });
+    }
+    while stack.len() > 1 {
+        let node = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(node);
+    }
+    let mut root = stack.pop().context("missing root dir")?;
+    root.sort(options.sort);
+
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", options.input.display())?;
+    root.write_children(&mut stdout, &mut String::new())?;
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use crate::output::HashRecord;
+use crate::util::format_hex;
+use crate::util::open_archive;
+use anyhow::bail;
+use anyhow::Context;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+use wolf_rpg_data::sanitize_components;
+
+#[derive(Debug, clap::Args)]
+#[command(about = "check that an archive is consistent and every file in it can be read")]
+pub struct Options {
+    #[arg(help = "the path to the archive")]
+    pub input: PathBuf,
+
+    #[arg(
+        long,
+        help = "also check the extracted files in --dir against a manifest written by \"extract --manifest\""
+    )]
+    pub manifest: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value = "out",
+        help = "the dir the files in the manifest were extracted to"
+    )]
+    pub dir: PathBuf,
+}
+
+pub fn exec(options: Options) -> anyhow::Result<()> {
+    let reader = open_archive(&options.input)?;
+    let num_files = reader.stats()?.file_count;
+
+    let mut num_errors = 0;
+    if let Err(errors) = reader.validate() {
+        num_errors += errors.len();
+        for error in errors {
+            eprintln!("{:#}", anyhow::Error::new(error));
+        }
+    }
+
+    if let Some(manifest) = options.manifest.as_ref() {
+        num_errors += check_manifest(&options.dir, manifest)?;
+    }
+
+    if num_errors != 0 {
+        bail!("found {num_errors} problems");
+    }
+
+    println!("{num_files} files ok");
+
+    Ok(())
+}
+
+/// Check extracted files against a hash manifest, printing each mismatch.
+///
+/// The files are read from the dir they were extracted to, so changes to them after extraction are caught.
+/// This returns the number of mismatches.
+fn check_manifest(dir: &Path, manifest: &Path) -> anyhow::Result<usize> {
+    let file = File::open(manifest)
+        .with_context(|| format!("failed to open \"{}\"", manifest.display()))?;
+    let hash_records: Vec<HashRecord> = serde_json::from_reader(BufReader::new(file))
+        .with_context(|| format!("failed to parse \"{}\"", manifest.display()))?;
+
+    let mut num_errors = 0;
+    for hash_record in hash_records {
+        let path = hash_record.path.as_str();
+        let components: Vec<_> = path.split('/').collect();
+        let file_path = dir.join(
+            sanitize_components(&components)
+                .with_context(|| format!("invalid path \"{path}\" in the manifest"))?,
+        );
+
+        let mut file = match File::open(&file_path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!(
+                    "\"{path}\" is in the manifest, but \"{}\" does not exist",
+                    file_path.display()
+                );
+                num_errors += 1;
+                continue;
+            }
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to open \"{}\"", file_path.display()))
+            }
+        };
+
+        let mut hasher = Sha256::new();
+        let size = std::io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("failed to read \"{}\"", file_path.display()))?;
+
+        if size != hash_record.size {
+            eprintln!(
+                "\"{path}\" should be {} bytes, but is {size} bytes",
+                hash_record.size,
+            );
+            num_errors += 1;
+            continue;
+        }
+
+        let sha256 = format_hex(&hasher.finalize());
+        if sha256 != hash_record.sha256 {
+            eprintln!(
+                "\"{path}\" should have the SHA-256 {}, but has {sha256}",
+                hash_record.sha256
+            );
+            num_errors += 1;
+        }
+    }
+
+    Ok(num_errors)
+}
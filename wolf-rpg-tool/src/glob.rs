@@ -0,0 +1,89 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+/// A simple glob pattern, matched against `/`-separated paths.
+///
+/// `*` matches any run of characters within a path component,
+/// `?` matches a single character within a path component,
+/// and `**` matches any number of whole path components.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    pattern: Vec<char>,
+}
+
+impl Glob {
+    /// Make a new glob from a pattern.
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.chars().collect(),
+        }
+    }
+
+    /// Check if this glob matches the given path.
+    pub fn is_match(&self, path: &str) -> bool {
+        let path: Vec<char> = path.chars().collect();
+        match_inner(&self.pattern, &path)
+    }
+}
+
+impl FromStr for Glob {
+    type Err = Infallible;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(pattern))
+    }
+}
+
+fn match_inner(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            if match_inner(rest, path) {
+                return true;
+            }
+            path.iter()
+                .enumerate()
+                .filter(|(_, c)| **c == '/')
+                .any(|(i, _)| match_inner(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| match_inner(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let component_len = path.iter().position(|c| *c == '/').unwrap_or(path.len());
+            (0..=component_len).any(|i| match_inner(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => match path {
+            [c, path @ ..] if *c != '/' => match_inner(rest, path),
+            _ => false,
+        },
+        [p, rest @ ..] => match path {
+            [c, path @ ..] if c == p => match_inner(rest, path),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glob_matches() {
+        let glob = Glob::new("**/*.png");
+        assert!(glob.is_match("Data/Picture/Pic1.png"));
+        assert!(glob.is_match("Pic1.png"));
+        assert!(!glob.is_match("Data/Picture/Pic1.jpg"));
+
+        let glob = Glob::new("Data/MapData/**");
+        assert!(glob.is_match("Data/MapData/Map001.mps"));
+        assert!(glob.is_match("Data/MapData/Sub/Map001.mps"));
+        assert!(!glob.is_match("Data/Picture/Pic1.png"));
+
+        let glob = Glob::new("Data/*.txt");
+        assert!(glob.is_match("Data/a.txt"));
+        assert!(!glob.is_match("Data/Sub/a.txt"));
+
+        let glob = Glob::new("Map00?.mps");
+        assert!(glob.is_match("Map001.mps"));
+        assert!(!glob.is_match("Map0001.mps"));
+    }
+}
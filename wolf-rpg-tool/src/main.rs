@@ -1,44 +1,112 @@
-use anyhow::Context;
-use std::fs::File;
-use std::path::PathBuf;
-use wolf_rpg_data::ArchiveReader;
+mod commands;
+mod glob;
+mod output;
+mod util;
 
-fn main() -> anyhow::Result<()> {
-    let path = std::env::args().nth(1).context("missing path")?;
-    let file = File::open(path)?;
-    let mut reader = ArchiveReader::new(file);
+use self::util::parse_key_string;
+use anyhow::Context;
+use clap::Parser;
+use wolf_rpg_data::ArchiveReaderBuilder;
+use wolf_rpg_data::KeyString;
 
-    let output = PathBuf::from("out");
+#[derive(Debug, clap::Parser)]
+#[command(about = "a tool for compiled WolfRPG assets", next_display_order = 100)]
+struct Options {
+    #[arg(
+        short,
+        long,
+        global = true,
+        action = clap::ArgAction::Count,
+        help = "print more output: once for info, twice for debug, and three times for trace, like \"-vv\""
+    )]
+    verbose: u8,
 
-    std::fs::create_dir_all(&output)?;
+    #[arg(short, long, global = true, help = "only print errors")]
+    quiet: bool,
 
-    reader.read_header()?;
+    #[arg(
+        long,
+        global = true,
+        value_parser = parse_key_string,
+        help = "the key string of the archive, as hex, like \"38 50 40 28 72 4F 21 70 3B 73 35 38\". This is also used to encrypt archives made by \"pack\"."
+    )]
+    key: Option<KeyString>,
 
-    dbg!(&reader);
+    #[arg(
+        long,
+        global = true,
+        help = "the code page of file names, like 932 or 65001. This overrides the code page in the archive header."
+    )]
+    code_page: Option<u64>,
 
-    let root_dir = reader.get_root_dir()?.context("no root dir")?;
+    #[command(subcommand)]
+    subcommand: Subcommand,
+}
 
-    for entry in reader.walk_dir(root_dir)? {
-        let entry = entry?;
-        let file = entry.file();
-        let path_components = entry.path_components();
+#[derive(Debug, clap::Subcommand)]
+enum Subcommand {
+    Cat(self::commands::cat::Options),
+    Diff(self::commands::diff::Options),
+    Extract(self::commands::extract::Options),
+    ExtractFile(self::commands::extract_file::Options),
+    Info(self::commands::info::Options),
+    List(self::commands::list::Options),
+    Manifest(self::commands::manifest::Options),
+    Pack(self::commands::pack::Options),
+    Search(self::commands::search::Options),
+    Tar(self::commands::tar::Options),
+    Tree(self::commands::tree::Options),
+    Verify(self::commands::verify::Options),
+}
 
-        dbg!(path_components);
+fn main() -> anyhow::Result<()> {
+    let options = Options::parse();
 
-        let mut output = output.clone();
-        output.extend(path_components);
+    // RUST_LOG takes precedence over the verbosity flags.
+    let log_level = if options.quiet {
+        log::LevelFilter::Error
+    } else {
+        match options.verbose {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    };
+    env_logger::Builder::new()
+        .filter_level(log_level)
+        .parse_default_env()
+        .init();
 
-        if file.is_dir() {
-            std::fs::create_dir_all(output)?;
-        } else {
-            let mut reader = reader.get_file_reader(file)?;
+    let mut archive_reader_builder = ArchiveReaderBuilder::new();
+    if let Some(key_string) = options.key {
+        archive_reader_builder = archive_reader_builder.key_string(key_string);
+    }
+    if let Some(code_page) = options.code_page {
+        let encoding = wolf_rpg_data::encoding_from_code_page(code_page)
+            .with_context(|| format!("unsupported code page {code_page}"))?;
+        archive_reader_builder = archive_reader_builder.encoding(encoding);
+    }
+    self::util::set_archive_reader_builder(archive_reader_builder);
 
-            let mut file = File::create(output)?;
-            std::io::copy(&mut reader, &mut file)?;
+    match options.subcommand {
+        Subcommand::Cat(options) => self::commands::cat::exec(options)?,
+        Subcommand::Diff(options) => self::commands::diff::exec(options)?,
+        Subcommand::Extract(options) => self::commands::extract::exec(options)?,
+        Subcommand::ExtractFile(options) => self::commands::extract_file::exec(options)?,
+        Subcommand::Info(options) => self::commands::info::exec(options)?,
+        Subcommand::List(options) => self::commands::list::exec(options)?,
+        Subcommand::Manifest(options) => self::commands::manifest::exec(options)?,
+        Subcommand::Pack(mut pack_options) => {
+            // The global key string is for reading archives, but it is also used for packing.
+            pack_options.key = options.key;
+            self::commands::pack::exec(pack_options)?
         }
+        Subcommand::Search(options) => self::commands::search::exec(options)?,
+        Subcommand::Tar(options) => self::commands::tar::exec(options)?,
+        Subcommand::Tree(options) => self::commands::tree::exec(options)?,
+        Subcommand::Verify(options) => self::commands::verify::exec(options)?,
     }
 
-    println!("Hello, world!");
-
     Ok(())
 }
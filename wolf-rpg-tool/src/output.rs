@@ -0,0 +1,133 @@
+use std::io::Write;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+use wolf_rpg_data::Attributes;
+use wolf_rpg_data::FileEntry;
+
+/// The format of a listing.
+#[derive(Debug, Copy, Clone)]
+pub enum OutputFormat {
+    /// Text meant for people.
+    Text,
+
+    /// A CSV with a header row.
+    Csv,
+
+    /// A JSON array.
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(Self::Text),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unknown format \"{value}\", expected \"text\", \"csv\", or \"json\""
+            )),
+        }
+    }
+}
+
+/// An entry in a machine-readable listing.
+#[derive(Debug, serde::Serialize)]
+pub struct EntryRecord {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub compressed_size: Option<u64>,
+    pub attributes: Attributes,
+
+    /// The modified time, in seconds since the Unix epoch.
+    pub modified: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<u32>,
+}
+
+impl EntryRecord {
+    /// Make a record for a file entry at the given path.
+    pub fn new(path: String, file: &FileEntry) -> Self {
+        let modified = file
+            .file_times()
+            .modified()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs());
+
+        Self {
+            path,
+            is_dir: file.is_dir(),
+            size: file.size(),
+            compressed_size: file.compressed_size(),
+            attributes: file.get_attributes(),
+            modified,
+            crc32: None,
+        }
+    }
+}
+
+/// An extracted file in a hash manifest.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct HashRecord {
+    /// The `/`-separated path the file was extracted to, relative to the output dir.
+    pub path: String,
+    pub size: u64,
+
+    /// The SHA-256 of the file's contents, as lowercase hex.
+    pub sha256: String,
+}
+
+/// Write records as a pretty JSON array.
+pub fn write_json<W>(mut writer: W, records: &[EntryRecord]) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    serde_json::to_writer_pretty(&mut writer, records)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+/// Write records as a CSV.
+///
+/// Missing values are left empty.
+/// The crc32 column is only written if any record has one.
+pub fn write_csv<W>(writer: W, records: &[EntryRecord]) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let has_crc32 = records.iter().any(|record| record.crc32.is_some());
+    let to_string = |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    let mut writer = csv::Writer::from_writer(writer);
+    let mut header = vec!["path", "size", "compressed", "attrs", "mtime"];
+    if has_crc32 {
+        header.push("crc32");
+    }
+    writer.write_record(header)?;
+
+    for record in records.iter() {
+        let mut row = vec![
+            record.path.clone(),
+            record.size.to_string(),
+            to_string(record.compressed_size),
+            record.attributes.bits().to_string(),
+            to_string(record.modified),
+        ];
+        if has_crc32 {
+            row.push(
+                record
+                    .crc32
+                    .map(|crc32| format!("{crc32:08X}"))
+                    .unwrap_or_default(),
+            );
+        }
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use sha2::Digest;
+use sha2::Sha256;
+use std::path::Path;
+use std::sync::OnceLock;
+use wolf_rpg_data::ArchiveReader;
+use wolf_rpg_data::ArchiveReaderBuilder;
+use wolf_rpg_data::FileEntry;
+use wolf_rpg_data::KeyString;
+use wolf_rpg_data::SharedFile;
+
+/// The options for opening archives, from the global CLI options.
+static ARCHIVE_READER_BUILDER: OnceLock<ArchiveReaderBuilder> = OnceLock::new();
+
+/// Set the options used by [`open_archive`].
+///
+/// This should be called once, before any archives are opened.
+pub fn set_archive_reader_builder(builder: ArchiveReaderBuilder) {
+    // Archives opened before this used the defaults, so this is a bug in the caller.
+    assert!(
+        ARCHIVE_READER_BUILDER.set(builder).is_ok(),
+        "archive reader options already set"
+    );
+}
+
+/// Open an archive and read its header.
+///
+/// If the file is not an archive, it is scanned for an embedded archive, like one appended to a game executable.
+/// The scan uses the global key, so embedded archives with a custom key are found too.
+///
+/// The archive is opened as a [`SharedFile`],
+/// so files can be read from multiple threads at once with [`ArchiveReader::get_file_reader_owned`].
+pub fn open_archive(path: &Path) -> anyhow::Result<ArchiveReader<SharedFile>> {
+    let file =
+        SharedFile::open(path).with_context(|| format!("failed to open \"{}\"", path.display()))?;
+    let builder = ARCHIVE_READER_BUILDER.get_or_init(ArchiveReaderBuilder::new);
+    let mut reader = builder.build(file);
+    match reader.read_header() {
+        Ok(()) => return Ok(reader),
+        Err(wolf_rpg_data::Error::InvalidMagic { .. }) => {}
+        Err(error) => return Err(error.into()),
+    }
+
+    let mut file = reader.into_inner();
+    let offset = builder
+        .find_archive_offset(&mut file)?
+        .with_context(|| format!("no archive found in \"{}\"", path.display()))?;
+    log::info!("found an embedded archive at offset {offset}");
+
+    let mut reader = builder.clone().base_offset(offset).build(file);
+    reader.read_header()?;
+
+    Ok(reader)
+}
+
+/// Format a size in bytes with binary units, like `1.5 KiB`.
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in UNITS[1..].iter() {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{value:.1} {unit}")
+}
+
+/// Parse a key string from hex.
+pub fn parse_key_string(value: &str) -> Result<KeyString, String> {
+    KeyString::from_hex(value).map_err(|error| error.to_string())
+}
+
+/// Hash the contents of a file with SHA-256.
+pub fn hash_file(reader: &ArchiveReader<SharedFile>, file: &FileEntry) -> anyhow::Result<[u8; 32]> {
+    let mut file_reader = reader.get_file_reader(file)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file_reader, &mut hasher)?;
+
+    Ok(hasher.finalize().into())
+}
+
+/// Format bytes as lowercase hex.
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}